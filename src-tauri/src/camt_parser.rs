@@ -0,0 +1,432 @@
+// ISO 20022 camt.053（账户对账单）/ camt.054（借贷记通知）/ camt.052（账户报告）解析
+//
+// 每笔交易明细（TxDtls）展开为一行，没有明细的记录（Ntry）本身为一行；明细中缺少的金额、
+// 借贷方向等取所在记录的值。展开后的表写入临时 CSV，与 CSV、Excel 数据源走相同的加载和清洗流程。
+
+use anyhow::{bail, Context, Result};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 展开后的列，可直接在字段映射中作为源列名使用
+pub const CAMT_COLUMNS: [&str; 24] = [
+    "statement_id",
+    "account",
+    "entry_reference",
+    "account_servicer_reference",
+    "booking_date",
+    "value_date",
+    "status",
+    "credit_debit",
+    "amount",
+    "signed_amount",
+    "currency",
+    "end_to_end_id",
+    "transaction_id",
+    "instruction_id",
+    "bank_tx_code",
+    "proprietary_tx_code",
+    "debtor_name",
+    "debtor_account",
+    "creditor_name",
+    "creditor_account",
+    "counterparty_name",
+    "counterparty_account",
+    "remittance_info",
+    "additional_info",
+];
+
+/// 对账单的顶层元素：camt.053 / camt.054 / camt.052
+const DOCUMENT_TYPES: [&str; 3] = ["BkToCstmrStmt", "BkToCstmrDbtCdtNtfctn", "BkToCstmrAcctRpt"];
+
+/// 对账单、通知或报告（与 DOCUMENT_TYPES 对应）
+const STATEMENT_ELEMENTS: [&str; 3] = ["Stmt", "Ntfctn", "Rpt"];
+
+type Fields = HashMap<&'static str, String>;
+
+#[derive(Default)]
+struct Entry {
+    fields: Fields,
+    transactions: Vec<Fields>,
+}
+
+/// 按扩展名判断是否为 XML 对账单
+pub fn is_camt_file(file_path: &str) -> bool {
+    Path::new(file_path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.eq_ignore_ascii_case("xml"))
+        .unwrap_or(false)
+}
+
+/// 解析对账单，返回展开后的行（列顺序同 CAMT_COLUMNS）
+pub fn parse_file(file_path: &str) -> Result<Vec<Vec<String>>> {
+    let content = fs::read_to_string(file_path)
+        .with_context(|| format!("无法读取XML文件: {}", file_path))?;
+    parse(content.trim_start_matches('\u{feff}'))
+}
+
+pub fn parse(xml: &str) -> Result<Vec<Vec<String>>> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut path: Vec<String> = Vec::new();
+    let mut is_camt = false;
+    let mut statement = Fields::new();
+    let mut entry: Option<Entry> = None;
+    let mut rows = Vec::new();
+
+    loop {
+        let event = reader
+            .read_event()
+            .with_context(|| format!("XML格式错误（位置 {}）", reader.buffer_position()))?;
+        match event {
+            Event::Start(e) => {
+                let name = local_name(&e);
+                if DOCUMENT_TYPES.contains(&name.as_str()) {
+                    is_camt = true;
+                }
+                path.push(name);
+                match path.last().map(|s| s.as_str()) {
+                    Some("Ntry") => entry = Some(Entry::default()),
+                    Some("TxDtls") => {
+                        if let Some(entry) = entry.as_mut() {
+                            entry.transactions.push(Fields::new());
+                        }
+                    }
+                    Some("Amt") => {
+                        // 币种在金额元素的 Ccy 属性中
+                        if let Some(currency) = attribute(&e, "Ccy")? {
+                            set_field(&path, &mut statement, entry.as_mut(), "Ccy", currency);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Event::Text(e) => {
+                let value = e.unescape().context("XML文本解码失败")?.trim().to_string();
+                if !value.is_empty() {
+                    set_field(&path, &mut statement, entry.as_mut(), "", value);
+                }
+            }
+            Event::End(_) => {
+                match path.last().map(|s| s.as_str()) {
+                    Some("Ntry") => {
+                        if let Some(entry) = entry.take() {
+                            emit_rows(&statement, entry, &mut rows);
+                        }
+                    }
+                    Some(name) if STATEMENT_ELEMENTS.contains(&name) => statement.clear(),
+                    _ => {}
+                }
+                path.pop();
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if !is_camt {
+        bail!("不是 camt.053 / camt.054 格式的对账单（缺少 BkToCstmrStmt 或 BkToCstmrDbtCdtNtfctn 元素）");
+    }
+    Ok(rows)
+}
+
+/// 把对账单展开后写入临时 CSV 文件（第一行为列名），返回临时文件路径，调用方加载后负责删除
+pub fn convert_to_csv(file_path: &str) -> Result<PathBuf> {
+    let rows = parse_file(file_path)?;
+
+    let csv_path = std::env::temp_dir().join(format!("file-compare-{}.csv", uuid::Uuid::new_v4()));
+    let mut writer = csv::Writer::from_path(&csv_path).context("无法创建临时CSV文件")?;
+    writer.write_record(CAMT_COLUMNS)?;
+    for row in rows {
+        writer.write_record(&row)?;
+    }
+    writer.flush()?;
+
+    Ok(csv_path)
+}
+
+fn local_name(e: &BytesStart) -> String {
+    String::from_utf8_lossy(e.local_name().as_ref()).into_owned()
+}
+
+fn attribute(e: &BytesStart, name: &str) -> Result<Option<String>> {
+    match e.try_get_attribute(name)? {
+        Some(attr) => Ok(Some(attr.unescape_value()?.trim().to_string())),
+        None => Ok(None),
+    }
+}
+
+/// 按元素路径写入对账单、记录或交易明细的字段；suffix 为 "Ccy" 时表示金额元素的币种属性
+fn set_field(
+    path: &[String],
+    statement: &mut Fields,
+    entry: Option<&mut Entry>,
+    suffix: &str,
+    value: String,
+) {
+    let relative = |start: usize| {
+        let mut relative = path[start + 1..].join("/");
+        if !suffix.is_empty() {
+            relative.push('/');
+            relative.push_str(suffix);
+        }
+        relative
+    };
+
+    let tx_start = path.iter().rposition(|p| p == "TxDtls");
+    let entry_start = path.iter().rposition(|p| p == "Ntry");
+    let statement_start = path.iter().rposition(|p| STATEMENT_ELEMENTS.contains(&p.as_str()));
+
+    match (entry, entry_start, tx_start) {
+        (Some(entry), Some(_), Some(tx_start)) => {
+            if let (Some(field), Some(tx)) = (transaction_field(&relative(tx_start)), entry.transactions.last_mut()) {
+                append_field(tx, field, value);
+            }
+        }
+        (Some(entry), Some(entry_start), None) => {
+            if let Some(field) = entry_field(&relative(entry_start)) {
+                append_field(&mut entry.fields, field, value);
+            }
+        }
+        _ => {
+            if let Some(start) = statement_start {
+                if let Some(field) = statement_field(&relative(start)) {
+                    statement.entry(field).or_insert(value);
+                }
+            }
+        }
+    }
+}
+
+/// 附言、交易码等可能分多个元素给出的字段依次拼接，其余字段保留第一个值
+fn append_field(fields: &mut Fields, field: &'static str, value: String) {
+    let separator = match field {
+        "remittance_info" | "additional_info" => " ",
+        "bank_tx_code" => "/",
+        _ => {
+            fields.entry(field).or_insert(value);
+            return;
+        }
+    };
+    fields
+        .entry(field)
+        .and_modify(|existing| {
+            existing.push_str(separator);
+            existing.push_str(&value);
+        })
+        .or_insert(value);
+}
+
+fn statement_field(path: &str) -> Option<&'static str> {
+    match path {
+        "Id" => Some("statement_id"),
+        "Acct/Id/IBAN" | "Acct/Id/Othr/Id" => Some("account"),
+        // 账户币种，记录金额未带币种时使用
+        "Acct/Ccy" => Some("currency"),
+        _ => None,
+    }
+}
+
+fn entry_field(path: &str) -> Option<&'static str> {
+    match path {
+        "NtryRef" => Some("entry_reference"),
+        "AcctSvcrRef" => Some("account_servicer_reference"),
+        "Amt" => Some("amount"),
+        "Amt/Ccy" => Some("currency"),
+        "CdtDbtInd" => Some("credit_debit"),
+        // camt.053.001.02 为 <Sts>BOOK</Sts>，001.08 起为 <Sts><Cd>BOOK</Cd></Sts>
+        "Sts" | "Sts/Cd" | "Sts/Prtry" => Some("status"),
+        "BookgDt/Dt" | "BookgDt/DtTm" => Some("booking_date"),
+        "ValDt/Dt" | "ValDt/DtTm" => Some("value_date"),
+        "BkTxCd/Domn/Cd" | "BkTxCd/Domn/Fmly/Cd" | "BkTxCd/Domn/Fmly/SubFmlyCd" => Some("bank_tx_code"),
+        "BkTxCd/Prtry/Cd" => Some("proprietary_tx_code"),
+        "AddtlNtryInf" => Some("additional_info"),
+        _ => None,
+    }
+}
+
+fn transaction_field(path: &str) -> Option<&'static str> {
+    // camt.053.001.08 起参与方多一层 Pty
+    let path = path
+        .replace("RltdPties/Dbtr/Pty/", "RltdPties/Dbtr/")
+        .replace("RltdPties/Cdtr/Pty/", "RltdPties/Cdtr/");
+    match path.as_str() {
+        "Refs/EndToEndId" => Some("end_to_end_id"),
+        "Refs/TxId" => Some("transaction_id"),
+        "Refs/InstrId" => Some("instruction_id"),
+        "Refs/AcctSvcrRef" => Some("account_servicer_reference"),
+        "Amt" | "AmtDtls/TxAmt/Amt" => Some("amount"),
+        "Amt/Ccy" | "AmtDtls/TxAmt/Amt/Ccy" => Some("currency"),
+        "CdtDbtInd" => Some("credit_debit"),
+        "BkTxCd/Domn/Cd" | "BkTxCd/Domn/Fmly/Cd" | "BkTxCd/Domn/Fmly/SubFmlyCd" => Some("bank_tx_code"),
+        "BkTxCd/Prtry/Cd" => Some("proprietary_tx_code"),
+        "RltdPties/Dbtr/Nm" => Some("debtor_name"),
+        "RltdPties/DbtrAcct/Id/IBAN" | "RltdPties/DbtrAcct/Id/Othr/Id" => Some("debtor_account"),
+        "RltdPties/Cdtr/Nm" => Some("creditor_name"),
+        "RltdPties/CdtrAcct/Id/IBAN" | "RltdPties/CdtrAcct/Id/Othr/Id" => Some("creditor_account"),
+        "RmtInf/Ustrd" | "RmtInf/Strd/CdtrRefInf/Ref" => Some("remittance_info"),
+        "AddtlTxInf" => Some("additional_info"),
+        _ => None,
+    }
+}
+
+/// 记录展开为行：交易明细的字段优先，其次为记录、对账单的字段
+/// 批量记录（多条交易明细）的金额是整批合计，明细未给出金额时留空，不取记录金额
+fn emit_rows(statement: &Fields, entry: Entry, rows: &mut Vec<Vec<String>>) {
+    let is_batch = entry.transactions.len() > 1;
+    let transactions = if entry.transactions.is_empty() {
+        vec![Fields::new()]
+    } else {
+        entry.transactions
+    };
+
+    let missing_amounts = transactions.iter().filter(|tx| !tx.contains_key("amount")).count();
+    if is_batch && missing_amounts > 0 {
+        log::warn!(
+            "camt 批量记录 {} 中有 {} 条交易明细没有金额，金额留空",
+            entry.fields.get("entry_reference").map(String::as_str).unwrap_or("（无 NtryRef）"),
+            missing_amounts
+        );
+    }
+
+    for transaction in transactions {
+        let value = |column: &str| {
+            let inherited = || {
+                if is_batch && column == "amount" {
+                    None
+                } else {
+                    entry.fields.get(column).or_else(|| statement.get(column))
+                }
+            };
+            transaction
+                .get(column)
+                .or_else(inherited)
+                .cloned()
+                .unwrap_or_default()
+        };
+
+        let credit_debit = value("credit_debit");
+        let amount = value("amount");
+        let is_debit = credit_debit == "DBIT";
+        let (counterparty_name, counterparty_account) = if is_debit {
+            (value("creditor_name"), value("creditor_account"))
+        } else {
+            (value("debtor_name"), value("debtor_account"))
+        };
+
+        let row = CAMT_COLUMNS
+            .iter()
+            .map(|column| match *column {
+                "signed_amount" if is_debit && !amount.is_empty() => format!("-{}", amount),
+                "signed_amount" => amount.clone(),
+                "counterparty_name" => counterparty_name.clone(),
+                "counterparty_account" => counterparty_account.clone(),
+                column => value(column),
+            })
+            .collect();
+        rows.push(row);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(row: &[String], name: &str) -> String {
+        let index = CAMT_COLUMNS.iter().position(|c| *c == name).unwrap();
+        row[index].clone()
+    }
+
+    const BATCH_STATEMENT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.08">
+  <BkToCstmrStmt>
+    <Stmt>
+      <Id>STMT-1</Id>
+      <Acct><Id><IBAN>BR1800360305000010009795493C1</IBAN></Id><Ccy>BRL</Ccy></Acct>
+      <Ntry>
+        <NtryRef>BATCH-1</NtryRef>
+        <Amt Ccy="BRL">150.00</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <Sts><Cd>BOOK</Cd></Sts>
+        <BookgDt><Dt>2025-12-30</Dt></BookgDt>
+        <NtryDtls>
+          <TxDtls>
+            <Refs><EndToEndId>E2E-1</EndToEndId></Refs>
+            <Amt Ccy="BRL">100.00</Amt>
+            <RltdPties><Cdtr><Pty><Nm>Loja A</Nm></Pty></Cdtr></RltdPties>
+          </TxDtls>
+          <TxDtls>
+            <Refs><EndToEndId>E2E-2</EndToEndId></Refs>
+            <Amt Ccy="BRL">50.00</Amt>
+            <RltdPties><Cdtr><Pty><Nm>Loja B</Nm></Pty></Cdtr></RltdPties>
+          </TxDtls>
+          <TxDtls>
+            <Refs><EndToEndId>E2E-3</EndToEndId></Refs>
+          </TxDtls>
+        </NtryDtls>
+      </Ntry>
+      <Ntry>
+        <NtryRef>SINGLE-1</NtryRef>
+        <Amt Ccy="BRL">20.00</Amt>
+        <CdtDbtInd>CRDT</CdtDbtInd>
+        <Sts><Cd>BOOK</Cd></Sts>
+        <NtryDtls>
+          <TxDtls>
+            <Refs><EndToEndId>E2E-4</EndToEndId></Refs>
+            <RltdPties><Dbtr><Pty><Nm>Cliente C</Nm></Pty></Dbtr></RltdPties>
+          </TxDtls>
+        </NtryDtls>
+      </Ntry>
+    </Stmt>
+  </BkToCstmrStmt>
+</Document>"#;
+
+    #[test]
+    fn expands_batch_entry_into_transaction_rows() {
+        let rows = parse(BATCH_STATEMENT).unwrap();
+        assert_eq!(rows.len(), 4);
+
+        let e2e: Vec<String> = rows.iter().map(|row| column(row, "end_to_end_id")).collect();
+        assert_eq!(e2e, ["E2E-1", "E2E-2", "E2E-3", "E2E-4"]);
+
+        // 明细金额各自保留，不继承整批合计
+        assert_eq!(column(&rows[0], "amount"), "100.00");
+        assert_eq!(column(&rows[0], "signed_amount"), "-100.00");
+        assert_eq!(column(&rows[1], "amount"), "50.00");
+        assert_eq!(column(&rows[2], "amount"), "");
+        assert_eq!(column(&rows[2], "signed_amount"), "");
+
+        // 其余字段取记录和对账单的值
+        for row in &rows[..3] {
+            assert_eq!(column(row, "entry_reference"), "BATCH-1");
+            assert_eq!(column(row, "credit_debit"), "DBIT");
+            assert_eq!(column(row, "status"), "BOOK");
+            assert_eq!(column(row, "booking_date"), "2025-12-30");
+            assert_eq!(column(row, "currency"), "BRL");
+            assert_eq!(column(row, "statement_id"), "STMT-1");
+            assert_eq!(column(row, "account"), "BR1800360305000010009795493C1");
+        }
+        assert_eq!(column(&rows[0], "counterparty_name"), "Loja A");
+        assert_eq!(column(&rows[1], "counterparty_name"), "Loja B");
+    }
+
+    #[test]
+    fn single_transaction_inherits_entry_amount() {
+        let rows = parse(BATCH_STATEMENT).unwrap();
+        let row = &rows[3];
+        assert_eq!(column(row, "entry_reference"), "SINGLE-1");
+        assert_eq!(column(row, "amount"), "20.00");
+        assert_eq!(column(row, "signed_amount"), "20.00");
+        assert_eq!(column(row, "counterparty_name"), "Cliente C");
+    }
+
+    #[test]
+    fn rejects_non_camt_documents() {
+        assert!(parse("<Document><Other/></Document>").is_err());
+        assert!(parse("<Document><BkToCstmrStmt></Stmt></Document>").is_err());
+    }
+}
@@ -1,10 +1,15 @@
+use crate::csv_reader;
+use crate::expression;
 use crate::models::*;
+use crate::processor::{parse_divisor, parse_status_range};
 use anyhow::{Context, Result};
+use regex::Regex;
 use std::fs;
 use std::path::PathBuf;
 
 const CONFIG_DIR_NAME: &str = "reconciliation_configs";
 const CONFIG_FILE_NAME: &str = "configs.json";
+const LOOKUP_TABLES_FILE_NAME: &str = "lookup_tables.json";
 
 pub struct ConfigManager {
     config_dir: PathBuf,
@@ -83,6 +88,8 @@ impl ConfigManager {
                 )
             })?;
         
+        self.validate_config(&config)?;
+
         // 生成新的ID和时间戳
         config.id = format!("config-{}", chrono::Utc::now().timestamp_millis());
         config.created_at = chrono::Utc::now().to_rfc3339();
@@ -90,5 +97,241 @@ impl ConfigManager {
         
         Ok(config)
     }
-}
 
+    /// 加载所有对照表
+    pub fn load_lookup_tables(&self) -> Result<Vec<LookupTable>> {
+        let file_path = self.config_dir.join(LOOKUP_TABLES_FILE_NAME);
+
+        if !file_path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read_to_string(&file_path)
+            .context("无法读取对照表文件")?;
+
+        let tables: Vec<LookupTable> = serde_json::from_str(&content)
+            .context("对照表文件格式错误")?;
+
+        Ok(tables)
+    }
+
+    /// 新增或更新对照表（按 ID），键去除首尾空格后不允许重复，表名不允许重复
+    pub fn save_lookup_table(&self, table: LookupTable) -> Result<LookupTable> {
+        let mut table = table;
+        if table.name.trim().is_empty() {
+            anyhow::bail!("对照表名称不能为空");
+        }
+        table.name = table.name.trim().to_string();
+
+        let mut keys = std::collections::HashSet::new();
+        for entry in &mut table.entries {
+            entry.key = entry.key.trim().to_string();
+            if !keys.insert(entry.key.clone()) {
+                anyhow::bail!("对照表 '{}' 中的键 '{}' 重复", table.name, entry.key);
+            }
+        }
+
+        let mut tables = self.load_lookup_tables()?;
+        if tables.iter().any(|t| t.name == table.name && t.id != table.id) {
+            anyhow::bail!("对照表名称 '{}' 已存在", table.name);
+        }
+        if table.id.is_empty() {
+            table.id = format!("lookup-{}", chrono::Utc::now().timestamp_millis());
+        }
+        table.updated_at = chrono::Utc::now().to_rfc3339();
+
+        if let Some(index) = tables.iter().position(|t| t.id == table.id) {
+            tables[index] = table.clone();
+        } else {
+            tables.push(table.clone());
+        }
+        self.save_lookup_tables(&tables)?;
+
+        Ok(table)
+    }
+
+    pub fn delete_lookup_table(&self, table_id: &str) -> Result<()> {
+        let mut tables = self.load_lookup_tables()?;
+        tables.retain(|t| t.id != table_id);
+        self.save_lookup_tables(&tables)
+    }
+
+    fn save_lookup_tables(&self, tables: &[LookupTable]) -> Result<()> {
+        let content = serde_json::to_string_pretty(tables)
+            .context("无法序列化对照表")?;
+
+        fs::write(self.config_dir.join(LOOKUP_TABLES_FILE_NAME), content)
+            .context("无法写入对照表文件")?;
+
+        Ok(())
+    }
+
+    /// 保存前校验配置：字段映射需要源列名或计算表达式，表达式和正则规则需能正确解析，
+    /// LOOKUP 规则引用的对照表需存在，状态映射、行过滤条件和金额符号规则可用
+    pub fn validate_config(&self, config: &ChannelConfig) -> Result<()> {
+        let lookup_names: Vec<String> = self
+            .load_lookup_tables()?
+            .into_iter()
+            .map(|t| t.name)
+            .collect();
+        let sources = [
+            (&config.source_a_name, &config.source_a_config),
+            (&config.source_b_name, &config.source_b_config),
+        ];
+        let status_mappings = [
+            (&config.source_a_name, &config.match_config.source_a_status_mapping),
+            (&config.source_b_name, &config.match_config.source_b_status_mapping),
+        ];
+        for (source_name, mappings) in status_mappings {
+            for mapping in mappings {
+                Self::validate_status_mapping(mapping).with_context(|| {
+                    format!("{} 的状态映射 '{}' 无效", source_name, mapping.target_status)
+                })?;
+            }
+        }
+
+        for (source_name, file_config) in sources {
+            csv_reader::validate_options(&file_config.file_options)
+                .with_context(|| format!("{} 的文件读取选项无效", source_name))?;
+            for filter in &file_config.row_filters {
+                if filter.action != "INCLUDE" && filter.action != "EXCLUDE" {
+                    anyhow::bail!("{} 的行过滤方式不支持: {}", source_name, filter.action);
+                }
+                Self::validate_rule_condition(&filter.condition)
+                    .with_context(|| format!("{} 的行过滤条件无效", source_name))?;
+            }
+            for mapping in &file_config.mappings {
+                for rule in &mapping.format_rules {
+                    Self::validate_format_rule(rule, &lookup_names).with_context(|| {
+                        format!("{} 的字段 '{}' 的格式规则无效", source_name, mapping.field_name)
+                    })?;
+                }
+                if let Some(sign) = &mapping.sign {
+                    Self::validate_amount_sign(sign).with_context(|| {
+                        format!("{} 的字段 '{}' 的金额符号规则无效", source_name, mapping.field_name)
+                    })?;
+                }
+                let uses_debit_credit = mapping.sign.as_ref().is_some_and(|s| s.mode == "DEBIT_CREDIT");
+                if mapping.expression.trim().is_empty() {
+                    if mapping.source_column.trim().is_empty() && !uses_debit_credit {
+                        anyhow::bail!(
+                            "{} 的字段 '{}' 未配置源列名或计算表达式",
+                            source_name,
+                            mapping.field_name
+                        );
+                    }
+                    continue;
+                }
+                expression::parse(&mapping.expression).with_context(|| {
+                    format!(
+                        "{} 的字段 '{}' 的表达式无效: {}",
+                        source_name, mapping.field_name, mapping.expression
+                    )
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 校验格式规则：正则可编译，提取的捕获组存在，对照表存在，生效条件可用
+    fn validate_format_rule(rule: &FormatRule, lookup_names: &[String]) -> Result<()> {
+        if let Some(condition) = &rule.condition {
+            Self::validate_rule_condition(condition)?;
+        }
+        if rule.operation == "LOOKUP" {
+            if !lookup_names.iter().any(|name| name == rule.value.trim()) {
+                anyhow::bail!("对照表 '{}' 不存在", rule.value);
+            }
+            if !matches!(rule.missing_policy.as_str(), "" | "KEEP" | "NULL" | "FLAG") {
+                anyhow::bail!("不支持的未匹配处理方式: {}", rule.missing_policy);
+            }
+            return Ok(());
+        }
+        if rule.operation == "DIVIDE_NUMBER" {
+            parse_divisor(&rule.value)?;
+            return Ok(());
+        }
+        if rule.operation != "REGEX_EXTRACT" && rule.operation != "REGEX_REPLACE" {
+            return Ok(());
+        }
+
+        if rule.value.is_empty() {
+            anyhow::bail!("{} 规则未配置正则表达式", rule.operation);
+        }
+        let regex = Regex::new(&rule.value)
+            .with_context(|| format!("正则表达式无效: {}", rule.value))?;
+
+        if rule.operation == "REGEX_EXTRACT" {
+            let group = rule.group.unwrap_or(1);
+            let group_count = regex.captures_len() - 1;
+            if group > group_count {
+                anyhow::bail!(
+                    "捕获组 {} 不存在，正则 '{}' 只有 {} 个捕获组",
+                    group,
+                    rule.value,
+                    group_count
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_rule_condition(condition: &RuleCondition) -> Result<()> {
+        if condition.column.trim().is_empty() {
+            anyhow::bail!("规则条件未配置判断列");
+        }
+        match condition.operator.as_str() {
+            "EQUALS" | "NOT_EQUALS" | "IN" => {}
+            "REGEX" => {
+                Regex::new(&condition.value)
+                    .with_context(|| format!("条件正则无效: {}", condition.value))?;
+            }
+            "GT" | "GTE" | "LT" | "LTE" => {
+                condition
+                    .value
+                    .trim()
+                    .parse::<f64>()
+                    .with_context(|| format!("条件值不是有效数字: {}", condition.value))?;
+            }
+            other => anyhow::bail!("不支持的条件运算符: {}", other),
+        }
+        Ok(())
+    }
+
+    fn validate_amount_sign(sign: &AmountSignConfig) -> Result<()> {
+        match sign.mode.as_str() {
+            "DEBIT_CREDIT" => {
+                if sign.debit_column.trim().is_empty() || sign.credit_column.trim().is_empty() {
+                    anyhow::bail!("借贷分列模式需要同时配置借方列和贷方列");
+                }
+            }
+            "INDICATOR" => {
+                if sign.indicator_column.trim().is_empty() {
+                    anyhow::bail!("借贷标识模式需要配置标识列");
+                }
+            }
+            other => anyhow::bail!("不支持的金额符号模式: {}（应为 DEBIT_CREDIT 或 INDICATOR）", other),
+        }
+        Ok(())
+    }
+
+    /// 校验状态映射：匹配方式有效，正则可编译，状态码范围格式正确
+    fn validate_status_mapping(mapping: &StatusMapping) -> Result<()> {
+        let match_type = mapping.effective_match_type();
+        if !STATUS_MATCH_PRECEDENCE.contains(&match_type) {
+            anyhow::bail!("不支持的状态匹配方式: {}", match_type);
+        }
+        for pattern in &mapping.source_status {
+            match match_type {
+                "REGEX" => {
+                    Regex::new(pattern).with_context(|| format!("状态正则无效: {}", pattern))?;
+                }
+                "RANGE" => {
+                    parse_status_range(pattern)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
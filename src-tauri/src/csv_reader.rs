@@ -0,0 +1,165 @@
+// CSV 数据源读取：按文件读取选项处理编码、分隔符、引号和表头之前的说明行
+//
+// 表头预览和加载使用同一套选项：非 UTF-8 文件先转码为 UTF-8 临时文件，
+// 分隔符、引号和跳过行数同时用于 csv 解析和 DuckDB 的 read_csv。
+
+use crate::models::{SourceFileOptions, COLUMN_TYPE_OVERRIDES};
+use anyhow::{bail, Context, Result};
+use encoding_rs::{Encoding, UTF_8};
+use std::fs;
+use std::path::PathBuf;
+
+/// 字段分隔符，未配置时为逗号
+pub fn delimiter(options: &SourceFileOptions) -> Result<u8> {
+    match options.delimiter.as_str() {
+        "" => Ok(b','),
+        "\\t" | "\t" => Ok(b'\t'),
+        value if value.eq_ignore_ascii_case("TAB") => Ok(b'\t'),
+        value => single_byte(value).with_context(|| format!("分隔符必须是单个字符: {}", value)),
+    }
+}
+
+/// 引号字符，未配置时为双引号
+pub fn quote(options: &SourceFileOptions) -> Result<u8> {
+    match options.quote.as_str() {
+        "" => Ok(b'"'),
+        value => single_byte(value).with_context(|| format!("引号必须是单个字符: {}", value)),
+    }
+}
+
+fn single_byte(value: &str) -> Option<u8> {
+    match value.as_bytes() {
+        [byte] if byte.is_ascii() => Some(*byte),
+        _ => None,
+    }
+}
+
+/// 文件编码，未配置时为 UTF-8
+pub fn encoding(options: &SourceFileOptions) -> Result<&'static Encoding> {
+    let label = options.encoding.trim();
+    if label.is_empty() {
+        return Ok(UTF_8);
+    }
+    // encoding_rs 不识别 latin-1 这类写法
+    let label = match label.to_lowercase().as_str() {
+        "latin-1" | "latin_1" => "latin1".to_string(),
+        other => other.to_string(),
+    };
+    Encoding::for_label(label.as_bytes())
+        .with_context(|| format!("不支持的文件编码: {}", options.encoding))
+}
+
+/// 校验分隔符、引号、小数点、编码和列类型覆盖配置
+pub fn validate_options(options: &SourceFileOptions) -> Result<()> {
+    let delimiter = delimiter(options)?;
+    let quote = quote(options)?;
+    if delimiter == quote {
+        bail!("分隔符和引号不能相同");
+    }
+    match options.decimal_separator.trim() {
+        "" | "." => {}
+        "," => {
+            if delimiter == b',' {
+                bail!("小数点为逗号时，分隔符不能也是逗号");
+            }
+        }
+        other => bail!("不支持的小数点: {}（应为 . 或 ,）", other),
+    }
+    encoding(options)?;
+    for (column, column_type) in &options.column_types {
+        if !COLUMN_TYPE_OVERRIDES.contains(&column_type.trim().to_uppercase().as_str()) {
+            bail!(
+                "列 '{}' 的类型不支持: {}（可选: {}）",
+                column,
+                column_type,
+                COLUMN_TYPE_OVERRIDES.join(", ")
+            );
+        }
+    }
+    Ok(())
+}
+
+/// 按配置的编码读取文件内容（带 BOM 时以 BOM 为准，BOM 会被去掉）
+fn read_text(file_path: &str, options: &SourceFileOptions) -> Result<String> {
+    let bytes = fs::read(file_path).with_context(|| format!("无法打开CSV文件: {}", file_path))?;
+    let encoding = encoding(options)?;
+    let (text, actual, had_errors) = encoding.decode(&bytes);
+    if had_errors {
+        bail!(
+            "文件内容不是 {} 编码，请在文件读取选项中指定正确的编码（如 GBK、ISO-8859-1、UTF-16）",
+            actual.name()
+        );
+    }
+    Ok(text.into_owned())
+}
+
+/// 生成供 DuckDB 读取的 UTF-8 临时文件，返回临时文件路径（调用方负责删除）：非 UTF-8 文件先转码，
+/// 配置了 skip_footer 时去掉末尾的合计行等（末尾的空行不计入），避免这些行参与列类型识别和解析；
+/// 本身就是 UTF-8 且不需要去掉表尾的文件返回 None，直接读取原文件
+pub fn to_utf8_file(file_path: &str, options: &SourceFileOptions) -> Result<Option<PathBuf>> {
+    let bytes = fs::read(file_path).with_context(|| format!("无法打开CSV文件: {}", file_path))?;
+    let is_utf8 = match Encoding::for_bom(&bytes) {
+        Some((bom_encoding, _)) => bom_encoding == UTF_8,
+        None => encoding(options)? == UTF_8 && std::str::from_utf8(&bytes).is_ok(),
+    };
+    if is_utf8 && options.skip_footer == 0 {
+        return Ok(None);
+    }
+
+    let mut text = read_text(file_path, options)?;
+    if options.skip_footer > 0 {
+        text = without_footer(&text, options.skip_footer);
+    }
+    let csv_path = std::env::temp_dir().join(format!("file-compare-{}.csv", uuid::Uuid::new_v4()));
+    fs::write(&csv_path, text).context("无法创建临时CSV文件")?;
+    Ok(Some(csv_path))
+}
+
+/// 去掉末尾的空行后，再去掉最后 skip_footer 行
+fn without_footer(text: &str, skip_footer: usize) -> String {
+    let mut lines: Vec<&str> = text.lines().collect();
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+    lines.truncate(lines.len().saturating_sub(skip_footer));
+    let mut text = lines.join("\n");
+    text.push('\n');
+    text
+}
+
+/// 读取表头（header_row 从 1 开始，0 表示没有表头，此时按 DuckDB 的规则以列序号命名）
+pub fn read_headers(file_path: &str, options: &SourceFileOptions, header_row: usize) -> Result<Vec<String>> {
+    let text = read_text(file_path, options)?;
+
+    // 与 DuckDB 的 skip 一致：按行跳过表头之前的说明行
+    let mut rest = text.as_str();
+    for _ in 0..options.leading_rows(header_row) {
+        match rest.find('\n') {
+            Some(pos) => rest = &rest[pos + 1..],
+            None => rest = "",
+        }
+    }
+
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(delimiter(options)?)
+        .quote(quote(options)?)
+        .from_reader(rest.as_bytes());
+
+    let first = reader
+        .records()
+        .next()
+        .context("CSV文件在跳过的行之后没有内容")??;
+
+    if header_row == 0 {
+        return Ok(default_column_names(first.len()));
+    }
+    Ok(first.iter().map(|h| h.to_string()).collect())
+}
+
+/// 没有表头时 DuckDB 生成的列名：column0、column1……（列数超过 10 时序号补零对齐）
+pub fn default_column_names(count: usize) -> Vec<String> {
+    let width = count.saturating_sub(1).to_string().len();
+    (0..count).map(|i| format!("column{:0width$}", i, width = width)).collect()
+}
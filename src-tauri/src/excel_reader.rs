@@ -0,0 +1,148 @@
+// Excel 数据源读取：把指定工作表转换为临时 CSV，再走与 CSV 文件相同的加载和清洗流程
+//
+// 所有单元格都按文本输出：文本单元格原样保留（不丢失前导零），数字不使用科学计数法，
+// 日期时间单元格输出为清洗后统一使用的时间格式。
+
+use crate::csv_reader;
+use crate::models::SourceFileOptions;
+use crate::time_parser::TIME_FORMAT;
+use anyhow::{bail, Context, Result};
+use calamine::{open_workbook_auto, Data, Reader};
+use chrono::NaiveTime;
+use std::path::{Path, PathBuf};
+
+const EXCEL_EXTENSIONS: [&str; 4] = ["xlsx", "xlsm", "xlsb", "xls"];
+
+/// 按扩展名判断是否为 Excel 文件
+pub fn is_excel_file(file_path: &str) -> bool {
+    Path::new(file_path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| EXCEL_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// 列出工作簿中的所有工作表
+pub fn list_sheets(file_path: &str) -> Result<Vec<String>> {
+    let workbook = open_workbook_auto(file_path)
+        .with_context(|| format!("无法打开Excel文件: {}", file_path))?;
+    Ok(workbook.sheet_names())
+}
+
+/// 读取工作表的所有行（按工作表中的实际行号排列，开头的空行也会保留）
+fn read_sheet_rows(file_path: &str, options: &SourceFileOptions) -> Result<Vec<Vec<String>>> {
+    let sheet_name = options.sheet_name.as_str();
+    let mut workbook = open_workbook_auto(file_path)
+        .with_context(|| format!("无法打开Excel文件: {}", file_path))?;
+
+    let sheet_names = workbook.sheet_names();
+    let sheet = if sheet_name.trim().is_empty() {
+        sheet_names.first().cloned().context("Excel文件中没有工作表")?
+    } else {
+        sheet_names
+            .iter()
+            .find(|name| name.trim() == sheet_name.trim())
+            .cloned()
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "工作表 '{}' 不存在。可用工作表: {}",
+                    sheet_name,
+                    sheet_names.join(", ")
+                )
+            })?
+    };
+
+    let range = workbook
+        .worksheet_range(&sheet)
+        .with_context(|| format!("无法读取工作表: {}", sheet))?;
+
+    // range 从第一个非空单元格开始，补齐前面的空行，使表头行号与 Excel 中看到的一致
+    let start_row = range.start().map(|(row, _)| row as usize).unwrap_or(0);
+    let mut rows: Vec<Vec<String>> = vec![Vec::new(); start_row];
+    let decimal_comma = options.uses_decimal_comma();
+    rows.extend(
+        range
+            .rows()
+            .map(|row| row.iter().map(|cell| cell_to_string(cell, decimal_comma)).collect()),
+    );
+    Ok(rows)
+}
+
+/// 截取表头行到末尾 skip_footer 行之前的数据，去掉整行为空的行和整列为空的列（如说明文字所在的列），
+/// 并统一各行的列数
+fn table_rows(file_path: &str, options: &SourceFileOptions, header_row: usize) -> Result<Vec<Vec<String>>> {
+    let rows = read_sheet_rows(file_path, options)?;
+
+    let first = options.leading_rows(header_row);
+    let last = rows.len().saturating_sub(options.skip_footer);
+    if first >= last {
+        bail!(
+            "工作表中没有数据（共 {} 行，跳过开头 {} 行，忽略末尾 {} 行）",
+            rows.len(),
+            first,
+            options.skip_footer
+        );
+    }
+
+    let rows: Vec<&Vec<String>> = rows[first..last]
+        .iter()
+        .filter(|row| row.iter().any(|v| !v.trim().is_empty()))
+        .collect();
+
+    let width = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+    let columns: Vec<usize> = (0..width)
+        .filter(|&i| rows.iter().any(|r| r.get(i).is_some_and(|v| !v.trim().is_empty())))
+        .collect();
+
+    Ok(rows
+        .iter()
+        .map(|row| columns.iter().map(|&i| row.get(i).cloned().unwrap_or_default()).collect())
+        .collect())
+}
+
+/// 读取表头行（header_row 从 1 开始，0 表示没有表头，此时与 CSV 一样按列序号命名）
+pub fn read_headers(file_path: &str, options: &SourceFileOptions, header_row: usize) -> Result<Vec<String>> {
+    let rows = table_rows(file_path, options, header_row)?;
+    let width = rows.first().map(|r| r.len()).unwrap_or(0);
+    if header_row == 0 {
+        return Ok(csv_reader::default_column_names(width));
+    }
+    Ok(rows.into_iter().next().unwrap_or_default())
+}
+
+/// 把工作表转换为临时 CSV 文件，返回临时文件路径，调用方加载后负责删除
+pub fn convert_to_csv(file_path: &str, options: &SourceFileOptions, header_row: usize) -> Result<PathBuf> {
+    let rows = table_rows(file_path, options, header_row)?;
+
+    let csv_path = std::env::temp_dir().join(format!("file-compare-{}.csv", uuid::Uuid::new_v4()));
+    let mut writer = csv::Writer::from_path(&csv_path).context("无法创建临时CSV文件")?;
+    for row in rows {
+        writer.write_record(&row)?;
+    }
+    writer.flush()?;
+
+    Ok(csv_path)
+}
+
+/// 小数点配置为逗号时，数字单元格也以逗号作小数点输出，与文本形式的金额一起在清洗时统一转换
+fn cell_to_string(cell: &Data, decimal_comma: bool) -> String {
+    match cell {
+        Data::Empty | Data::Error(_) => String::new(),
+        Data::String(value) | Data::DateTimeIso(value) | Data::DurationIso(value) => value.clone(),
+        Data::Int(value) => value.to_string(),
+        // f64 的 Display 不使用科学计数法，整数值也不会带 ".0"
+        Data::Float(value) if decimal_comma => value.to_string().replace('.', ","),
+        Data::Float(value) => value.to_string(),
+        Data::Bool(value) => value.to_string().to_uppercase(),
+        Data::DateTime(value) => match value.as_datetime() {
+            Some(datetime) if value.is_datetime() => {
+                if datetime.time() == NaiveTime::MIN {
+                    datetime.format("%Y-%m-%d").to_string()
+                } else {
+                    datetime.format(TIME_FORMAT).to_string()
+                }
+            }
+            _ => value.as_f64().to_string(),
+        },
+    }
+}
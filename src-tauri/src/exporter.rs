@@ -3,6 +3,11 @@ use anyhow::Result;
 use std::collections::HashMap;
 use std::fs::File;
 use csv::Writer;
+use rust_decimal::Decimal;
+use std::str::FromStr;
+
+/// 结果中附加的信息字段：汇率、手续费、行过滤排除原因
+const EXTRA_FIELDS: [&str; 4] = ["fxRate", "feeExpected", "feeVariance", "excluded_by"];
 
 pub fn export_to_csv(
     results: &ReconciliationResult,
@@ -14,6 +19,12 @@ pub fn export_to_csv(
         "onlyInA" => write_csv(&results.only_in_a, file_path)?,
         "onlyInB" => write_csv(&results.only_in_b, file_path)?,
         "diffAmount" => write_csv_diff_amount(&results.diff_amount, file_path)?,
+        "matchedWithTolerance" => write_csv_diff_amount(&results.matched_with_tolerance, file_path)?,
+        "statusMismatch" => write_csv(&results.status_mismatch, file_path)?,
+        "groupedMatched" => write_csv_diff_amount(&results.grouped_matched, file_path)?,
+        "suggestedMatches" => write_csv(&results.suggested_matches, file_path)?,
+        "excludedA" => write_csv(&results.excluded_a, file_path)?,
+        "excludedB" => write_csv(&results.excluded_b, file_path)?,
         "all" => {
             return export_all_to_csv(results, file_path);
         }
@@ -32,6 +43,12 @@ fn export_all_to_csv(results: &ReconciliationResult, base_path: &str) -> Result<
     write_csv(&results.only_in_a, &format!("{}_仅在数据源A.csv", base_path))?;
     write_csv(&results.only_in_b, &format!("{}_仅在数据源B.csv", base_path))?;
     write_csv_diff_amount(&results.diff_amount, &format!("{}_金额差异.csv", base_path))?;
+    write_csv_diff_amount(&results.matched_with_tolerance, &format!("{}_容差匹配.csv", base_path))?;
+    write_csv(&results.status_mismatch, &format!("{}_状态不一致.csv", base_path))?;
+    write_csv_diff_amount(&results.grouped_matched, &format!("{}_分组匹配.csv", base_path))?;
+    write_csv(&results.suggested_matches, &format!("{}_疑似匹配.csv", base_path))?;
+    write_csv(&results.excluded_a, &format!("{}_已排除_数据源A.csv", base_path))?;
+    write_csv(&results.excluded_b, &format!("{}_已排除_数据源B.csv", base_path))?;
     
     Ok(())
 }
@@ -98,6 +115,13 @@ fn write_csv(data: &[HashMap<String, serde_json::Value>], file_path: &str) -> Re
         }
     }
 
+    // 6. 添加匹配信息字段（如 match_pass）及附加信息字段
+    for header in &all_headers {
+        if !headers.contains(header) && (header.starts_with("match_") || EXTRA_FIELDS.contains(&header.as_str())) {
+            headers.push(header.clone());
+        }
+    }
+
     // 写入表头
     writer.write_record(&headers)?;
 
@@ -163,26 +187,27 @@ fn write_csv_diff_amount(data: &[HashMap<String, serde_json::Value>], file_path:
     }
     
     // 3. 特别处理金额字段：分别显示sourceA和sourceB的金额
-    let mut source_a_amount: Option<String> = None;
-    let mut source_b_amount: Option<String> = None;
-    
-    for header in &all_headers {
-        if header.to_lowercase().contains("amount") {
-            if header.to_lowercase().contains("sourcea") {
-                source_a_amount = Some(header.clone());
-            } else if header.to_lowercase().contains("sourceb") {
-                source_b_amount = Some(header.clone());
-            }
-        }
-    }
+    // 币种换算、手续费还原后同一侧有原金额和调整后金额（xxxConverted / xxxGross）多列，差异按最终调整后的金额计算
+    let amount_headers = |side: &str| -> Vec<String> {
+        let mut side_headers: Vec<String> = all_headers
+            .iter()
+            .filter(|h| {
+                let lower = h.to_lowercase();
+                lower.contains("amount") && lower.contains(side)
+            })
+            .cloned()
+            .collect();
+        side_headers.sort_by_key(|h| (h.ends_with("Converted") || h.ends_with("Gross"), h.len()));
+        side_headers
+    };
+    let source_a_amounts = amount_headers("sourcea");
+    let source_b_amounts = amount_headers("sourceb");
+    let source_a_amount = source_a_amounts.last().cloned();
+    let source_b_amount = source_b_amounts.last().cloned();
     
     // 按照 sourceA -> sourceB 的顺序添加金额字段
-    if let Some(amount) = source_a_amount.clone() {
-        headers.push(amount);
-    }
-    if let Some(amount) = source_b_amount.clone() {
-        headers.push(amount);
-    }
+    headers.extend(source_a_amounts.iter().cloned());
+    headers.extend(source_b_amounts.iter().cloned());
     
     // 添加金额差异字段（如果存在的话，我们可以计算）
     let has_amount_diff = source_a_amount.is_some() && source_b_amount.is_some();
@@ -205,6 +230,13 @@ fn write_csv_diff_amount(data: &[HashMap<String, serde_json::Value>], file_path:
         }
     }
 
+    // 6. 添加匹配信息字段（如 match_pass）及附加信息字段
+    for header in &all_headers {
+        if !headers.contains(header) && (header.starts_with("match_") || EXTRA_FIELDS.contains(&header.as_str())) {
+            headers.push(header.clone());
+        }
+    }
+
     // 如果需要计算金额差异，添加差异列
     if has_amount_diff {
         headers.push("金额差异".to_string());
@@ -234,14 +266,11 @@ fn write_csv_diff_amount(data: &[HashMap<String, serde_json::Value>], file_path:
         // 计算并添加金额差异
         if has_amount_diff {
             if let (Some(a_field), Some(b_field)) = (&source_a_amount, &source_b_amount) {
-                let amount_a = row.get(a_field)
-                    .and_then(|v| v.as_f64())
-                    .unwrap_or(0.0);
-                let amount_b = row.get(b_field)
-                    .and_then(|v| v.as_f64())
-                    .unwrap_or(0.0);
+                // 使用精确小数计算，差异保留两侧金额中较多的小数位
+                let amount_a = row.get(a_field).and_then(decimal_value).unwrap_or_default();
+                let amount_b = row.get(b_field).and_then(decimal_value).unwrap_or_default();
                 let diff = amount_a - amount_b;
-                record.push(format!("{:.2}", diff));
+                record.push(diff.to_string());
             }
         }
         
@@ -252,3 +281,16 @@ fn write_csv_diff_amount(data: &[HashMap<String, serde_json::Value>], file_path:
     Ok(())
 }
 
+/// 将 JSON 中的金额（精确小数字符串或数字）解析为 Decimal
+fn decimal_value(value: &serde_json::Value) -> Option<Decimal> {
+    match value {
+        serde_json::Value::String(s) => Decimal::from_str(s.trim()).ok(),
+        serde_json::Value::Number(n) => {
+            let text = n.to_string();
+            Decimal::from_str(&text)
+                .or_else(|_| Decimal::from_scientific(&text))
+                .ok()
+        }
+        _ => None,
+    }
+}
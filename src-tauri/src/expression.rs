@@ -0,0 +1,469 @@
+// 字段映射中的计算表达式
+//
+// 支持的语法：
+// - 列引用：`amount`、`"Branch Code"`（含空格等特殊字符的列名用双引号）
+// - 字面量：数字 `100`、`0.5`，字符串 `'-'`
+// - 运算：`+ - * /`、一元负号、括号
+// - 函数：concat、coalesce、nullif、upper、lower、trim、substr、replace、length、abs、round
+//
+// 表达式先解析为语法树并校验列名和函数，再生成 SQL，不会把用户输入直接拼接到 SQL 中。
+
+use anyhow::{bail, Result};
+
+/// 参与算术运算的列统一转换为的定点小数类型
+const NUMERIC_TYPE: &str = "DECIMAL(18,4)";
+
+/// 允许的函数：(名称, 最少参数个数, 最多参数个数)
+const FUNCTIONS: [(&str, usize, usize); 11] = [
+    ("concat", 1, usize::MAX),
+    ("coalesce", 1, usize::MAX),
+    ("nullif", 2, 2),
+    ("upper", 1, 1),
+    ("lower", 1, 1),
+    ("trim", 1, 1),
+    ("substr", 2, 3),
+    ("replace", 3, 3),
+    ("length", 1, 1),
+    ("abs", 1, 1),
+    ("round", 1, 2),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Column(String),
+    Number(String),
+    Text(String),
+    Negate(Box<Expr>),
+    Binary(char, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    QuotedIdent(String),
+    Number(String),
+    Text(String),
+    Op(char),
+    LParen,
+    RParen,
+    Comma,
+}
+
+/// 解析表达式并校验函数名与参数个数
+pub fn parse(input: &str) -> Result<Expr> {
+    let tokens = tokenize(input)?;
+    if tokens.is_empty() {
+        bail!("表达式为空");
+    }
+
+    let mut parser = Parser { tokens, pos: 0 };
+    let expr = parser.parse_expr()?;
+    if parser.pos < parser.tokens.len() {
+        bail!("表达式第 {} 个符号之后有多余内容", parser.pos);
+    }
+    Ok(expr)
+}
+
+/// 解析表达式、校验引用的列都存在，并生成 DuckDB SQL
+///
+/// decimal_comma_columns 为小数点是逗号的文本列，在数值运算中先转换为带小数点的数字；
+/// numeric 为 true 时（金额、手续费字段）整个表达式按数值计算
+pub fn compile(
+    input: &str,
+    available_columns: &[String],
+    decimal_comma_columns: &[String],
+    numeric: bool,
+) -> Result<String> {
+    let expr = parse(input)?;
+    for column in expr.columns() {
+        if !available_columns.iter().any(|c| c == column) {
+            bail!(
+                "表达式引用的列 '{}' 不存在。可用列: {}",
+                column,
+                available_columns.join(", ")
+            );
+        }
+    }
+    if numeric {
+        Ok(expr.numeric_sql(decimal_comma_columns))
+    } else {
+        Ok(expr.to_sql(decimal_comma_columns))
+    }
+}
+
+/// 小数点为逗号的数字文本：去掉千分位的点，再把逗号换成点（1.234,56 → 1234.56）
+pub fn decimal_comma_to_point(value_sql: &str) -> String {
+    format!(
+        "REPLACE(REPLACE(TRIM(CAST({} AS VARCHAR)), '.', ''), ',', '.')",
+        value_sql
+    )
+}
+
+impl Expr {
+    /// 表达式引用的所有列名
+    pub fn columns(&self) -> Vec<&str> {
+        let mut columns = Vec::new();
+        self.collect_columns(&mut columns);
+        columns
+    }
+
+    fn collect_columns<'a>(&'a self, columns: &mut Vec<&'a str>) {
+        match self {
+            Expr::Column(name) => {
+                if !columns.contains(&name.as_str()) {
+                    columns.push(name);
+                }
+            }
+            Expr::Negate(inner) => inner.collect_columns(columns),
+            Expr::Binary(_, left, right) => {
+                left.collect_columns(columns);
+                right.collect_columns(columns);
+            }
+            Expr::Call(_, args) => args.iter().for_each(|arg| arg.collect_columns(columns)),
+            Expr::Number(_) | Expr::Text(_) => {}
+        }
+    }
+
+    /// 生成 SQL：列名加引号，字符串转义；算术运算的列和函数结果转换为定点小数，空字符串视为 NULL
+    pub fn to_sql(&self, decimal_comma_columns: &[String]) -> String {
+        match self {
+            Expr::Column(name) => quote_identifier(name),
+            Expr::Number(value) => value.clone(),
+            Expr::Text(value) => format!("'{}'", value.replace('\'', "''")),
+            Expr::Negate(inner) => format!("(-{})", inner.numeric_sql(decimal_comma_columns)),
+            Expr::Binary(op, left, right) => format!(
+                "({} {} {})",
+                left.numeric_sql(decimal_comma_columns),
+                op,
+                right.numeric_sql(decimal_comma_columns)
+            ),
+            Expr::Call(name, args) => Self::call_sql(name, args, decimal_comma_columns, false),
+        }
+    }
+
+    fn numeric_sql(&self, decimal_comma_columns: &[String]) -> String {
+        match self {
+            Expr::Number(_) | Expr::Negate(_) | Expr::Binary(..) => self.to_sql(decimal_comma_columns),
+            // coalesce、nullif 的参数与结果同为数值
+            Expr::Call(name, args) if name == "coalesce" || name == "nullif" => {
+                Self::call_sql(name, args, decimal_comma_columns, true)
+            }
+            Expr::Column(name) if decimal_comma_columns.contains(name) => format!(
+                "CAST(NULLIF({}, '') AS {})",
+                decimal_comma_to_point(&quote_identifier(name)),
+                NUMERIC_TYPE
+            ),
+            _ => format!(
+                "CAST(NULLIF(TRIM(CAST({} AS VARCHAR)), '') AS {})",
+                self.to_sql(decimal_comma_columns),
+                NUMERIC_TYPE
+            ),
+        }
+    }
+
+    fn call_sql(name: &str, args: &[Expr], decimal_comma_columns: &[String], numeric: bool) -> String {
+        // abs、round 的第一个参数是数值
+        let numeric_first = name == "abs" || name == "round";
+        let args: Vec<String> = args
+            .iter()
+            .enumerate()
+            .map(|(i, arg)| {
+                if numeric || (i == 0 && numeric_first) {
+                    arg.numeric_sql(decimal_comma_columns)
+                } else {
+                    arg.to_sql(decimal_comma_columns)
+                }
+            })
+            .collect();
+        format!("{}({})", name.to_uppercase(), args.join(", "))
+    }
+}
+
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '(' => {
+                tokens.push(Token::LParen);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::RParen);
+                i += 1;
+            }
+            ',' => {
+                tokens.push(Token::Comma);
+                i += 1;
+            }
+            '+' | '-' | '*' | '/' => {
+                tokens.push(Token::Op(c));
+                i += 1;
+            }
+            '\'' | '"' => {
+                // 字符串和带引号的列名，连续两个引号表示引号本身
+                let quote = c;
+                let mut value = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => bail!("表达式中的引号 {} 未闭合", quote),
+                        Some(&ch) if ch == quote => {
+                            if chars.get(i + 1) == Some(&quote) {
+                                value.push(quote);
+                                i += 2;
+                            } else {
+                                i += 1;
+                                break;
+                            }
+                        }
+                        Some(&ch) => {
+                            value.push(ch);
+                            i += 1;
+                        }
+                    }
+                }
+                if quote == '\'' {
+                    tokens.push(Token::Text(value));
+                } else {
+                    if value.is_empty() {
+                        bail!("列名不能为空");
+                    }
+                    tokens.push(Token::QuotedIdent(value));
+                }
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                let number: String = chars[start..i].iter().collect();
+                if number.parse::<f64>().is_err() {
+                    bail!("无效的数字: {}", number);
+                }
+                tokens.push(Token::Number(number));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                tokens.push(Token::Ident(chars[start..i].iter().collect()));
+            }
+            _ => bail!("表达式中不支持的字符: '{}'", c),
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    // expr := term (('+' | '-') term)*
+    fn parse_expr(&mut self) -> Result<Expr> {
+        let mut left = self.parse_term()?;
+        while let Some(Token::Op(op @ ('+' | '-'))) = self.peek().cloned() {
+            self.pos += 1;
+            let right = self.parse_term()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    // term := unary (('*' | '/') unary)*
+    fn parse_term(&mut self) -> Result<Expr> {
+        let mut left = self.parse_unary()?;
+        while let Some(Token::Op(op @ ('*' | '/'))) = self.peek().cloned() {
+            self.pos += 1;
+            let right = self.parse_unary()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    // unary := '-' unary | primary
+    fn parse_unary(&mut self) -> Result<Expr> {
+        if let Some(Token::Op('-')) = self.peek() {
+            self.pos += 1;
+            return Ok(Expr::Negate(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    // primary := NUMBER | STRING | "列名" | 列名 | 函数(参数, ...) | '(' expr ')'
+    fn parse_primary(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::Number(value)) => Ok(Expr::Number(value)),
+            Some(Token::Text(value)) => Ok(Expr::Text(value)),
+            Some(Token::QuotedIdent(name)) => Ok(Expr::Column(name)),
+            Some(Token::Ident(name)) => {
+                if self.peek() == Some(&Token::LParen) {
+                    self.pos += 1;
+                    self.parse_call(name)
+                } else {
+                    Ok(Expr::Column(name))
+                }
+            }
+            Some(Token::LParen) => {
+                let expr = self.parse_expr()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(expr),
+                    _ => bail!("表达式缺少右括号"),
+                }
+            }
+            Some(token) => bail!("表达式中出现意外的符号: {:?}", token),
+            None => bail!("表达式不完整"),
+        }
+    }
+
+    fn parse_call(&mut self, name: String) -> Result<Expr> {
+        let function = name.to_lowercase();
+        let (_, min_args, max_args) = FUNCTIONS
+            .iter()
+            .find(|(f, _, _)| *f == function)
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<&str> = FUNCTIONS.iter().map(|(f, _, _)| *f).collect();
+                anyhow::anyhow!("不支持的函数: {}。可用函数: {}", name, names.join(", "))
+            })?;
+
+        let mut args = Vec::new();
+        if self.peek() == Some(&Token::RParen) {
+            self.pos += 1;
+        } else {
+            loop {
+                args.push(self.parse_expr()?);
+                match self.next() {
+                    Some(Token::Comma) => continue,
+                    Some(Token::RParen) => break,
+                    _ => bail!("函数 {} 的参数列表缺少右括号", name),
+                }
+            }
+        }
+
+        if args.len() < min_args || args.len() > max_args {
+            bail!("函数 {} 的参数个数不正确: {}", name, args.len());
+        }
+        Ok(Expr::Call(function, args))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn columns(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn quotes_identifiers_and_strings() {
+        let available = columns(&["a\"; DROP TABLE source_a; --"]);
+        let sql = compile(
+            "concat(\"a\"\"; DROP TABLE source_a; --\", 'x''; DROP TABLE source_b; --')",
+            &available,
+            &[],
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            sql,
+            "CONCAT(\"a\"\"; DROP TABLE source_a; --\", 'x''; DROP TABLE source_b; --')"
+        );
+    }
+
+    #[test]
+    fn rejects_injection_attempts() {
+        let available = columns(&["amount"]);
+        // 裸写的分号不是合法符号
+        assert!(compile("amount; DROP TABLE source_a", &available, &[], false).is_err());
+        // 带引号的列名必须是已有的列
+        assert!(compile("\"; DROP TABLE source_a; --\"", &available, &[], false).is_err());
+        // 引号未闭合
+        assert!(parse("'abc").is_err());
+        assert!(parse("\"amount").is_err());
+        // 白名单之外的函数
+        assert!(parse("read_csv('/etc/passwd')").is_err());
+        assert!(parse("amount + system('rm')").is_err());
+    }
+
+    #[test]
+    fn multiplication_binds_tighter_than_addition() {
+        let expr = parse("a + b * c").unwrap();
+        assert_eq!(
+            expr,
+            Expr::Binary(
+                '+',
+                Box::new(Expr::Column("a".to_string())),
+                Box::new(Expr::Binary(
+                    '*',
+                    Box::new(Expr::Column("b".to_string())),
+                    Box::new(Expr::Column("c".to_string()))
+                ))
+            )
+        );
+
+        let expr = parse("(a + b) * -c").unwrap();
+        assert_eq!(
+            expr,
+            Expr::Binary(
+                '*',
+                Box::new(Expr::Binary(
+                    '+',
+                    Box::new(Expr::Column("a".to_string())),
+                    Box::new(Expr::Column("b".to_string()))
+                )),
+                Box::new(Expr::Negate(Box::new(Expr::Column("c".to_string()))))
+            )
+        );
+
+        // 同级运算从左到右结合
+        let expr = parse("a - b - c").unwrap();
+        assert!(matches!(expr, Expr::Binary('-', left, _) if matches!(*left, Expr::Binary('-', ..))));
+    }
+
+    #[test]
+    fn validates_columns_and_argument_counts() {
+        let available = columns(&["amount"]);
+        assert!(compile("amount - fee", &available, &[], true).is_err());
+        assert!(parse("nullif(amount)").is_err());
+        assert!(parse("round(amount, 2").is_err());
+        assert!(parse("amount amount").is_err());
+    }
+
+    #[test]
+    fn converts_decimal_comma_columns_in_arithmetic() {
+        let available = columns(&["amount", "fee"]);
+        let sql = compile("amount - fee", &available, &columns(&["amount"]), true).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                "(CAST(NULLIF({}, '') AS DECIMAL(18,4)) - CAST(NULLIF(TRIM(CAST(\"fee\" AS VARCHAR)), '') AS DECIMAL(18,4)))",
+                decimal_comma_to_point("\"amount\"")
+            )
+        );
+    }
+}
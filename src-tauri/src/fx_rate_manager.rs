@@ -0,0 +1,142 @@
+use crate::models::FxRate;
+use anyhow::{bail, Context, Result};
+use rust_decimal::Decimal;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+pub struct FxRateManager {
+    rates_file: PathBuf,
+}
+
+impl FxRateManager {
+    pub fn new() -> Result<Self> {
+        // 与渠道配置、对照表同在用户主目录下的 .file-compare 中
+        let rates_dir = dirs::home_dir()
+            .context("无法获取用户主目录")?
+            .join(".file-compare")
+            .join("fx_rates");
+
+        fs::create_dir_all(&rates_dir)?;
+
+        Ok(Self {
+            rates_file: rates_dir.join("fx_rates.json"),
+        })
+    }
+
+    /// 加载所有汇率
+    pub fn load_rates(&self) -> Result<Vec<FxRate>> {
+        if !self.rates_file.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read_to_string(&self.rates_file)?;
+        let rates: Vec<FxRate> =
+            serde_json::from_str(&content).context("汇率数据格式不正确")?;
+        Ok(rates)
+    }
+
+    /// 保存所有汇率（保存前校验并规范化）
+    pub fn save_rates(&self, rates: &[FxRate]) -> Result<()> {
+        let mut normalized = Vec::with_capacity(rates.len());
+        for rate in rates {
+            normalized.push(Self::normalize_rate(rate)?);
+        }
+        normalized.sort_by(|a, b| {
+            (&a.base_currency, &a.quote_currency, &a.rate_date)
+                .cmp(&(&b.base_currency, &b.quote_currency, &b.rate_date))
+        });
+
+        let json = serde_json::to_string_pretty(&normalized)?;
+        fs::write(&self.rates_file, json)?;
+        Ok(())
+    }
+
+    /// 从 CSV 导入汇率，返回导入条数
+    /// 需要列：date、base_currency、quote_currency、rate（也接受 base/quote 或 from/to）
+    /// 同一日期同一币种对的汇率会被覆盖
+    pub fn import_csv(&self, file_path: &str) -> Result<usize> {
+        let mut reader = csv::Reader::from_path(file_path)
+            .with_context(|| format!("无法读取汇率文件: {}", file_path))?;
+
+        let headers: Vec<String> = reader
+            .headers()?
+            .iter()
+            .map(|h| h.trim().to_lowercase())
+            .collect();
+        let column = |names: &[&str]| -> Result<usize> {
+            headers
+                .iter()
+                .position(|h| names.contains(&h.as_str()))
+                .with_context(|| format!("汇率文件缺少列: {}", names.join(" / ")))
+        };
+        let date_col = column(&["date", "rate_date"])?;
+        let base_col = column(&["base_currency", "base", "from"])?;
+        let quote_col = column(&["quote_currency", "quote", "to"])?;
+        let rate_col = column(&["rate"])?;
+
+        let mut imported = Vec::new();
+        for (index, record) in reader.records().enumerate() {
+            let record = record?;
+            let field = |col: usize| record.get(col).unwrap_or("").to_string();
+            let rate = FxRate {
+                rate_date: field(date_col),
+                base_currency: field(base_col),
+                quote_currency: field(quote_col),
+                rate: field(rate_col),
+            };
+            // 表头占第 1 行
+            imported.push(
+                Self::normalize_rate(&rate).with_context(|| format!("汇率文件第 {} 行无效", index + 2))?,
+            );
+        }
+
+        let mut rates = self.load_rates()?;
+        rates.retain(|existing| {
+            !imported.iter().any(|r| {
+                r.rate_date == existing.rate_date
+                    && r.base_currency == existing.base_currency
+                    && r.quote_currency == existing.quote_currency
+            })
+        });
+        let count = imported.len();
+        rates.extend(imported);
+        self.save_rates(&rates)?;
+
+        Ok(count)
+    }
+
+    /// 校验汇率记录：日期为 YYYY-MM-DD，币种为三位字母代码，汇率为正数
+    fn normalize_rate(rate: &FxRate) -> Result<FxRate> {
+        let rate_date = chrono::NaiveDate::parse_from_str(rate.rate_date.trim(), "%Y-%m-%d")
+            .with_context(|| format!("日期格式不正确（应为 YYYY-MM-DD）: {}", rate.rate_date))?
+            .format("%Y-%m-%d")
+            .to_string();
+
+        let currency = |code: &str| -> Result<String> {
+            let code = code.trim().to_uppercase();
+            if code.len() != 3 || !code.chars().all(|c| c.is_ascii_alphabetic()) {
+                bail!("币种代码不正确: {}", code);
+            }
+            Ok(code)
+        };
+        let base_currency = currency(&rate.base_currency)?;
+        let quote_currency = currency(&rate.quote_currency)?;
+        if base_currency == quote_currency {
+            bail!("基础币种与报价币种相同: {}", base_currency);
+        }
+
+        let value = Decimal::from_str(rate.rate.trim())
+            .with_context(|| format!("汇率不是有效数字: {}", rate.rate))?;
+        if value <= Decimal::ZERO {
+            bail!("汇率必须大于 0: {}", rate.rate);
+        }
+
+        Ok(FxRate {
+            rate_date,
+            base_currency,
+            quote_currency,
+            rate: value.normalize().to_string(),
+        })
+    }
+}
@@ -5,22 +5,39 @@ mod config_manager;
 mod task_manager;
 mod order_manager;
 mod logger;
+mod time_parser;
+mod fx_rate_manager;
+mod expression;
+mod excel_reader;
+mod csv_reader;
+mod camt_parser;
 
 use models::*;
-use processor::DataProcessor;
+use processor::{DataProcessor, SourceFields};
 use exporter::export_to_csv;
 use config_manager::ConfigManager;
 use task_manager::TaskManager;
 use order_manager::{OrderManager, OrderFile, QueryCondition};
+use fx_rate_manager::FxRateManager;
 
 #[tauri::command]
-fn read_csv_headers(file_path: String, header_row: usize) -> Result<Vec<String>, String> {
+fn read_csv_headers(
+    file_path: String,
+    header_row: usize,
+    file_options: Option<SourceFileOptions>,
+) -> Result<Vec<String>, String> {
     let processor = DataProcessor::new().map_err(|e| e.to_string())?;
     processor
-        .read_csv_headers(&file_path, header_row)
+        .read_csv_headers(&file_path, header_row, &file_options.unwrap_or_default())
         .map_err(|e| e.to_string())
 }
 
+/// 列出 Excel 文件中的工作表
+#[tauri::command]
+fn list_excel_sheets(file_path: String) -> Result<Vec<String>, String> {
+    excel_reader::list_sheets(&file_path).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 fn reconcile(
     source_a_config: FileConfig,
@@ -48,34 +65,75 @@ fn reconcile(
             err_msg
         })?;
 
-    // Step 1: 加载 CSV 文件并保存到 DuckDB
+    // Step 1: 加载 CSV / Excel 文件并保存到 DuckDB
     processor
-        .load_csv_to_table(&source_a_config.file_path, "source_a", source_a_config.header)
+        .load_file_to_table(
+            &source_a_config.file_path,
+            "source_a",
+            source_a_config.header,
+            &source_a_config.file_options,
+            &source_a_mappings,
+        )
         .map_err(|e| format!("加载数据源A文件失败: {}", e))?;
 
     processor
-        .load_csv_to_table(&source_b_config.file_path, "source_b", source_b_config.header)
+        .load_file_to_table(
+            &source_b_config.file_path,
+            "source_b",
+            source_b_config.header,
+            &source_b_config.file_options,
+            &source_b_mappings,
+        )
         .map_err(|e| format!("加载数据源B文件失败: {}", e))?;
 
     // Step 2: 数据清洗
+    register_lookup_tables(&processor)?;
+
     processor
-        .apply_data_cleaning("source_a", &source_a_mappings)
+        .apply_data_cleaning(
+            "source_a",
+            &source_a_mappings,
+            &source_a_config.timezone,
+            &match_config.reconciliation_timezone,
+            &source_a_config.file_options.decimal_separator,
+        )
         .map_err(|e| format!("清洗数据源A失败: {}", e))?;
 
     processor
-        .apply_data_cleaning("source_b", &source_b_mappings)
+        .apply_data_cleaning(
+            "source_b",
+            &source_b_mappings,
+            &source_b_config.timezone,
+            &match_config.reconciliation_timezone,
+            &source_b_config.file_options.decimal_separator,
+        )
         .map_err(|e| format!("清洗数据源B失败: {}", e))?;
 
-    // Step 3: 去重
+    // Step 3: 行过滤（排除手续费行、余额行等）与去重
+    let mut excluded_a = processor
+        .apply_row_filters(
+            "source_a",
+            &source_a_config.row_filters,
+            &source_a_config.file_options.decimal_separator,
+        )
+        .map_err(|e| format!("过滤数据源A失败: {}", e))?;
+    let mut excluded_b = processor
+        .apply_row_filters(
+            "source_b",
+            &source_b_config.row_filters,
+            &source_b_config.file_options.decimal_separator,
+        )
+        .map_err(|e| format!("过滤数据源B失败: {}", e))?;
+
     if source_a_config.remove_duplicate {
         processor
-            .remove_duplicates("source_a", &match_config.source_a_id_field)
+            .remove_duplicates("source_a", &match_config.source_a_keys())
             .map_err(|e| format!("去重数据源A失败: {}", e))?;
     }
 
     if source_b_config.remove_duplicate {
         processor
-            .remove_duplicates("source_b", &match_config.source_b_id_field)
+            .remove_duplicates("source_b", &match_config.source_b_keys())
             .map_err(|e| format!("去重数据源B失败: {}", e))?;
     }
 
@@ -92,28 +150,45 @@ fn reconcile(
         .map(|m| m.field_name.clone())
         .unwrap_or_default();
 
-    let source_a_amount_field = source_a_mappings
-        .iter()
-        .find(|m| m.field_type == "OrderAmount")
-        .map(|m| m.field_name.clone())
-        .unwrap_or_default();
-
-    let source_b_amount_field = source_b_mappings
-        .iter()
-        .find(|m| m.field_type == "OrderAmount")
-        .map(|m| m.field_name.clone())
-        .unwrap_or_default();
+    // 清洗后的时间字段已统一换算到对账时区
+    let mut source_a_fields = SourceFields::from_mappings(&source_a_mappings, &match_config.reconciliation_timezone);
+    let mut source_b_fields = SourceFields::from_mappings(&source_b_mappings, &match_config.reconciliation_timezone);
 
+    // 记录未映射的原始状态，随任务返回便于补全配置
+    let mut unmapped_statuses = Vec::new();
     if !source_a_status_field.is_empty() {
-        processor
-            .normalize_status("source_a", &source_a_status_field, &match_config.source_a_status_mapping)
+        let unmapped = processor
+            .normalize_status(
+                "source_a",
+                &source_a_status_field,
+                &match_config.source_a_status_mapping,
+                &match_config.default_status,
+            )
             .map_err(|e| format!("标准化数据源A状态失败: {}", e))?;
+        unmapped_statuses.extend(unmapped.into_iter().map(|(raw_status, count)| UnmappedStatus {
+            source_name: source_a_config.source_name.clone(),
+            raw_status,
+            count,
+        }));
     }
 
     if !source_b_status_field.is_empty() {
-        processor
-            .normalize_status("source_b", &source_b_status_field, &match_config.source_b_status_mapping)
+        let unmapped = processor
+            .normalize_status(
+                "source_b",
+                &source_b_status_field,
+                &match_config.source_b_status_mapping,
+                &match_config.default_status,
+            )
             .map_err(|e| format!("标准化数据源B状态失败: {}", e))?;
+        unmapped_statuses.extend(unmapped.into_iter().map(|(raw_status, count)| UnmappedStatus {
+            source_name: source_b_config.source_name.clone(),
+            raw_status,
+            count,
+        }));
+    }
+    if !unmapped_statuses.is_empty() {
+        log::warn!("存在未映射的状态: {:?}", unmapped_statuses);
     }
 
     // Step 5: 获取清洗后的数据准备保存到 DuckDB
@@ -170,8 +245,19 @@ fn reconcile(
         
         if !historical_data.is_empty() {
             processor
-                .load_historical_data("source_a", historical_data)
+                .load_historical_data("source_a", historical_data, &source_a_mappings)
                 .map_err(|e| format!("导入历史数据源A失败: {}", e))?;
+
+            // 历史数据可能由订单管理上传或在修改过滤条件之前保存，同样按行过滤条件排除
+            excluded_a.extend(
+                processor
+                    .apply_row_filters(
+                        "source_a",
+                        &source_a_config.row_filters,
+                        &source_a_config.file_options.decimal_separator,
+                    )
+                    .map_err(|e| format!("过滤历史数据源A失败: {}", e))?,
+            );
         }
     }
 
@@ -194,20 +280,34 @@ fn reconcile(
         
         if !historical_data.is_empty() {
             processor
-                .load_historical_data("source_b", historical_data)
+                .load_historical_data("source_b", historical_data, &source_b_mappings)
                 .map_err(|e| format!("导入历史数据源B失败: {}", e))?;
+
+            // 历史数据可能由订单管理上传或在修改过滤条件之前保存，同样按行过滤条件排除
+            excluded_b.extend(
+                processor
+                    .apply_row_filters(
+                        "source_b",
+                        &source_b_config.row_filters,
+                        &source_b_config.file_options.decimal_separator,
+                    )
+                    .map_err(|e| format!("过滤历史数据源B失败: {}", e))?,
+            );
         }
     }
 
-    // Step 8: 执行对账
-    let result = processor
+    // Step 8: 币种换算、手续费还原（如启用）后执行对账
+    adjust_amounts(&processor, &match_config, &mut source_a_fields, &mut source_b_fields)?;
+
+    let mut result = processor
         .perform_reconciliation(
-            &match_config.source_a_id_field, 
-            &match_config.source_b_id_field,
-            &source_a_amount_field,
-            &source_b_amount_field
+            &match_config,
+            &source_a_fields,
+            &source_b_fields
         )
         .map_err(|e| format!("对账失败: {}", e))?;
+    result.excluded_a = excluded_a;
+    result.excluded_b = excluded_b;
 
     // Step 9: 创建任务记录
     let task_id = format!("task_{}", chrono::Utc::now().timestamp_millis());
@@ -225,16 +325,10 @@ fn reconcile(
         created_at,
         source_a_file_name: source_a_config.file_name.clone(),
         source_b_file_name: source_b_config.file_name.clone(),
-        stats: ReconciliationStats {
-            matched_count: result.matched.len(),
-            only_in_source_a_count: result.only_in_a.len(),
-            only_in_source_b_count: result.only_in_b.len(),
-            diff_amount_count: result.diff_amount.len(),
-            total_source_a: result.matched.len() + result.only_in_a.len() + result.diff_amount.len(),
-            total_source_b: result.matched.len() + result.only_in_b.len() + result.diff_amount.len(),
-        },
+        stats: build_stats(&result),
         used_historical_source_a: match_config.use_historical_source_a,
         used_historical_source_b: match_config.use_historical_source_b,
+        unmapped_statuses,
     };
 
     // Step 10: 保存任务
@@ -246,6 +340,68 @@ fn reconcile(
     Ok((task, result))
 }
 
+/// 加载对照表供清洗时的 LOOKUP 规则使用
+fn register_lookup_tables(processor: &DataProcessor) -> Result<(), String> {
+    let tables = ConfigManager::new()
+        .and_then(|manager| manager.load_lookup_tables())
+        .map_err(|e| format!("加载对照表失败: {}", e))?;
+    processor
+        .register_lookup_tables(&tables)
+        .map_err(|e| format!("注册对照表失败: {}", e))
+}
+
+/// 比较前调整金额：先按汇率换算币种（如启用），再将净额还原为总额（如启用手续费）
+fn adjust_amounts(
+    processor: &DataProcessor,
+    match_config: &MatchConfig,
+    source_a_fields: &mut SourceFields,
+    source_b_fields: &mut SourceFields,
+) -> Result<(), String> {
+    if match_config.currency_conversion.as_ref().is_some_and(|c| c.enabled) {
+        let rates = FxRateManager::new()
+            .and_then(|manager| manager.load_rates())
+            .map_err(|e| format!("加载汇率表失败: {}", e))?;
+        processor
+            .apply_currency_conversion(match_config, source_a_fields, source_b_fields, &rates)
+            .map_err(|e| format!("币种换算失败: {}", e))?;
+    }
+
+    processor
+        .apply_fee_adjustment(match_config, source_a_fields, source_b_fields)
+        .map_err(|e| format!("手续费计算失败: {}", e))
+}
+
+/// 根据对账结果汇总统计数据
+fn build_stats(result: &ReconciliationResult) -> ReconciliationStats {
+    let paired = result.matched.len()
+        + result.matched_with_tolerance.len()
+        + result.diff_amount.len()
+        + result.status_mismatch.len();
+    // 分组匹配按成员记录数计入两侧总数
+    let grouped_rows = |count_field: &str| -> usize {
+        result
+            .grouped_matched
+            .iter()
+            .filter_map(|row| row.get(count_field).and_then(|v| v.as_u64()))
+            .sum::<u64>() as usize
+    };
+
+    ReconciliationStats {
+        matched_count: result.matched.len(),
+        only_in_source_a_count: result.only_in_a.len(),
+        only_in_source_b_count: result.only_in_b.len(),
+        diff_amount_count: result.diff_amount.len(),
+        tolerance_matched_count: result.matched_with_tolerance.len(),
+        status_mismatch_count: result.status_mismatch.len(),
+        grouped_matched_count: result.grouped_matched.len(),
+        suggested_match_count: result.suggested_matches.len(),
+        excluded_source_a_count: result.excluded_a.len(),
+        excluded_source_b_count: result.excluded_b.len(),
+        total_source_a: paired + grouped_rows("sourceACount") + result.only_in_a.len(),
+        total_source_b: paired + grouped_rows("sourceBCount") + result.only_in_b.len(),
+    }
+}
+
 #[tauri::command]
 fn export_results(
     results: ReconciliationResult,
@@ -265,6 +421,7 @@ fn load_configs() -> Result<Vec<ChannelConfig>, String> {
 #[tauri::command]
 fn save_config(config: ChannelConfig) -> Result<(), String> {
     let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    manager.validate_config(&config).map_err(|e| format!("{:#}", e))?;
     let mut configs = manager.load_configs().map_err(|e| e.to_string())?;
     
     // 查找是否存在相同ID的配置
@@ -319,6 +476,46 @@ fn import_config(file_path: String) -> Result<ChannelConfig, String> {
     Ok(config)
 }
 
+// 对照表管理命令
+#[tauri::command]
+fn load_lookup_tables() -> Result<Vec<LookupTable>, String> {
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    manager.load_lookup_tables().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn save_lookup_table(table: LookupTable) -> Result<LookupTable, String> {
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    manager.save_lookup_table(table).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn delete_lookup_table(table_id: String) -> Result<(), String> {
+    let manager = ConfigManager::new().map_err(|e| e.to_string())?;
+    manager.delete_lookup_table(&table_id).map_err(|e| e.to_string())
+}
+
+// 汇率管理命令
+#[tauri::command]
+fn load_fx_rates() -> Result<Vec<FxRate>, String> {
+    let manager = FxRateManager::new().map_err(|e| e.to_string())?;
+    manager.load_rates().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn save_fx_rates(rates: Vec<FxRate>) -> Result<(), String> {
+    let manager = FxRateManager::new().map_err(|e| e.to_string())?;
+    manager.save_rates(&rates).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn import_fx_rates(file_path: String) -> Result<usize, String> {
+    let manager = FxRateManager::new().map_err(|e| e.to_string())?;
+    manager
+        .import_csv(&file_path)
+        .map_err(|e| format!("导入汇率失败: {}", e))
+}
+
 // 任务管理命令
 #[tauri::command]
 fn load_tasks() -> Result<Vec<ReconciliationTask>, String> {
@@ -344,6 +541,83 @@ fn get_tasks_by_config(config_id: String) -> Result<Vec<ReconciliationTask>, Str
     manager.get_tasks_by_config(&config_id).map_err(|e| e.to_string())
 }
 
+/// 人工确认疑似匹配：accept 为 true 时把该建议移入完全匹配（match_pass 为 MANUAL），并从两侧单边账中移除对应记录；
+/// 为 false 时只删除该建议，记录仍保留在单边账中。之后重新汇总统计并保存任务
+#[tauri::command]
+fn resolve_suggested_match(
+    task_id: String,
+    source_a_id: String,
+    source_b_id: String,
+    accept: bool,
+) -> Result<(ReconciliationTask, ReconciliationResult), String> {
+    let task_manager = TaskManager::new().map_err(|e| e.to_string())?;
+    let mut task = task_manager
+        .load_tasks()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .find(|t| t.task_id == task_id)
+        .ok_or_else(|| "任务不存在".to_string())?;
+    let mut result = task_manager
+        .load_task_result(&task_id)
+        .map_err(|e| e.to_string())?;
+
+    let position = result
+        .suggested_matches
+        .iter()
+        .position(|row| {
+            json_text(row.get("sourceAId")) == source_a_id && json_text(row.get("sourceBId")) == source_b_id
+        })
+        .ok_or_else(|| "疑似匹配不存在或已处理".to_string())?;
+
+    if accept {
+        let config_manager = ConfigManager::new().map_err(|e| e.to_string())?;
+        let configs = config_manager.load_configs().map_err(|e| e.to_string())?;
+        let config = configs
+            .iter()
+            .find(|c| c.id == task.config_id)
+            .ok_or_else(|| "配置不存在".to_string())?;
+
+        remove_first_by_id(&mut result.only_in_a, &config.match_config.source_a_id_field, &source_a_id)?;
+        remove_first_by_id(&mut result.only_in_b, &config.match_config.source_b_id_field, &source_b_id)?;
+
+        let mut matched = result.suggested_matches.remove(position);
+        matched.insert("match_pass".to_string(), serde_json::Value::String("MANUAL".to_string()));
+        result.matched.push(matched);
+    } else {
+        result.suggested_matches.remove(position);
+    }
+
+    task.stats = build_stats(&result);
+    task_manager
+        .save_task(&task, &result)
+        .map_err(|e| format!("保存任务失败: {}", e))?;
+
+    Ok((task, result))
+}
+
+/// 结果中的值按文本比较（ID 可能以字符串或数字保存）
+fn json_text(value: Option<&serde_json::Value>) -> String {
+    match value {
+        Some(serde_json::Value::String(text)) => text.clone(),
+        Some(serde_json::Value::Null) | None => String::new(),
+        Some(other) => other.to_string(),
+    }
+}
+
+/// 从单边账中移除第一条 ID 相同的记录
+fn remove_first_by_id(
+    rows: &mut Vec<std::collections::HashMap<String, serde_json::Value>>,
+    id_field: &str,
+    id: &str,
+) -> Result<(), String> {
+    let position = rows
+        .iter()
+        .position(|row| json_text(row.get(id_field)) == id)
+        .ok_or_else(|| format!("单边账中找不到 ID 为 '{}' 的记录", id))?;
+    rows.remove(position);
+    Ok(())
+}
+
 // Double Check 功能：扩大时间范围重新对账
 #[tauri::command]
 fn double_check_task(
@@ -411,54 +685,71 @@ fn double_check_task(
 
     // 注意：历史数据已经是清洗后的数据，不需要再次清洗
     processor
-        .load_historical_data("source_a", source_a_data)
+        .load_historical_data("source_a", source_a_data, &config.source_a_config.mappings)
         .map_err(|e| format!("导入数据源A失败: {}", e))?;
 
     processor
-        .load_historical_data("source_b", source_b_data)
+        .load_historical_data("source_b", source_b_data, &config.source_b_config.mappings)
         .map_err(|e| format!("导入数据源B失败: {}", e))?;
 
-    // 验证字段名是否存在
-    let source_a_id_field = config.match_config.source_a_id_field.clone();
-    let source_b_id_field = config.match_config.source_b_id_field.clone();
-    
+    // 按配置的行过滤条件排除不参与对账的行（如其他来源上传的历史数据中的手续费行）
+    let excluded_a = processor
+        .apply_row_filters(
+            "source_a",
+            &config.source_a_config.row_filters,
+            &config.source_a_config.file_options.decimal_separator,
+        )
+        .map_err(|e| format!("过滤数据源A失败: {}", e))?;
+    let excluded_b = processor
+        .apply_row_filters(
+            "source_b",
+            &config.source_b_config.row_filters,
+            &config.source_b_config.file_options.decimal_separator,
+        )
+        .map_err(|e| format!("过滤数据源B失败: {}", e))?;
+
     // 检查字段是否存在
     let source_a_columns = processor.get_table_columns("source_a")
         .map_err(|e| format!("获取数据源A字段失败: {}", e))?;
     let source_b_columns = processor.get_table_columns("source_b")
         .map_err(|e| format!("获取数据源B字段失败: {}", e))?;
     
-    if !source_a_columns.contains(&source_a_id_field) {
-        return Err(format!(
-            "配置中的字段名 '{}' 在数据源A中不存在。可用字段: {}",
-            source_a_id_field,
-            source_a_columns.join(", ")
-        ));
+    // 验证所有匹配轮次的键字段是否存在（支持组合键）
+    let match_passes = config.match_config.effective_match_passes();
+    for source_a_key in match_passes.iter().flat_map(|p| &p.source_a_key_fields) {
+        if !source_a_columns.contains(source_a_key) {
+            return Err(format!(
+                "配置中的字段名 '{}' 在数据源A中不存在。可用字段: {}",
+                source_a_key,
+                source_a_columns.join(", ")
+            ));
+        }
     }
     
-    if !source_b_columns.contains(&source_b_id_field) {
-        return Err(format!(
-            "配置中的字段名 '{}' 在数据源B中不存在。可用字段: {}",
-            source_b_id_field,
-            source_b_columns.join(", ")
-        ));
+    for source_b_key in match_passes.iter().flat_map(|p| &p.source_b_key_fields) {
+        if !source_b_columns.contains(source_b_key) {
+            return Err(format!(
+                "配置中的字段名 '{}' 在数据源B中不存在。可用字段: {}",
+                source_b_key,
+                source_b_columns.join(", ")
+            ));
+        }
     }
 
     // 查找金额字段（历史数据中已经是清洗后的字段名，如 sourceAAmount）
-    let source_a_amount_field = config.source_a_config.mappings
-        .iter()
-        .find(|m| m.field_type == "OrderAmount")
-        .map(|m| m.field_name.clone())
-        .unwrap_or_default();
-
-    let source_b_amount_field = config.source_b_config.mappings
-        .iter()
-        .find(|m| m.field_type == "OrderAmount")
-        .map(|m| m.field_name.clone())
-        .unwrap_or_default();
+    let mut source_a_fields = SourceFields::from_mappings(
+        &config.source_a_config.mappings,
+        &config.match_config.reconciliation_timezone,
+    );
+    let mut source_b_fields = SourceFields::from_mappings(
+        &config.source_b_config.mappings,
+        &config.match_config.reconciliation_timezone,
+    );
+    let source_a_amount_field = &source_a_fields.amount_field;
+    let source_b_amount_field = &source_b_fields.amount_field;
 
     // 验证金额字段是否存在（如果配置了）
-    if !source_a_amount_field.is_empty() && !source_a_columns.contains(&source_a_amount_field) {
+    if !source_a_amount_field.is_empty() && !source_a_columns.contains(source_a_amount_field) {
         return Err(format!(
             "配置中的金额字段名 '{}' 在数据源A中不存在。可用字段: {}",
             source_a_amount_field,
@@ -466,7 +757,7 @@ fn double_check_task(
         ));
     }
     
-    if !source_b_amount_field.is_empty() && !source_b_columns.contains(&source_b_amount_field) {
+    if !source_b_amount_field.is_empty() && !source_b_columns.contains(source_b_amount_field) {
         return Err(format!(
             "配置中的金额字段名 '{}' 在数据源B中不存在。可用字段: {}",
             source_b_amount_field,
@@ -475,15 +766,18 @@ fn double_check_task(
     }
 
     // 历史数据已经是清洗后的标准格式，状态已经被标准化为 normalized_status
-    // 不需要再次进行状态标准化，直接执行对账
-    let result = processor
+    // 不需要再次进行状态标准化，币种换算、手续费还原（如启用）后直接执行对账
+    adjust_amounts(&processor, &config.match_config, &mut source_a_fields, &mut source_b_fields)?;
+
+    let mut result = processor
         .perform_reconciliation(
-            &source_a_id_field,
-            &source_b_id_field,
-            &source_a_amount_field,
-            &source_b_amount_field,
+            &config.match_config,
+            &source_a_fields,
+            &source_b_fields,
         )
         .map_err(|e| format!("对账失败: {}", e))?;
+    result.excluded_a = excluded_a;
+    result.excluded_b = excluded_b;
 
     // 创建新任务记录
     let new_task_id = format!("task_{}_doublecheck", chrono::Utc::now().timestamp_millis());
@@ -501,16 +795,11 @@ fn double_check_task(
         created_at,
         source_a_file_name: format!("历史数据(±{}天)", extended_days),
         source_b_file_name: format!("历史数据(±{}天)", extended_days),
-        stats: ReconciliationStats {
-            matched_count: result.matched.len(),
-            only_in_source_a_count: result.only_in_a.len(),
-            only_in_source_b_count: result.only_in_b.len(),
-            diff_amount_count: result.diff_amount.len(),
-            total_source_a: result.matched.len() + result.only_in_a.len() + result.diff_amount.len(),
-            total_source_b: result.matched.len() + result.only_in_b.len() + result.diff_amount.len(),
-        },
+        stats: build_stats(&result),
         used_historical_source_a: true,
         used_historical_source_b: true,
+        // Double Check 直接使用已清洗的历史数据，不重新标准化状态
+        unmapped_statuses: Vec::new(),
     };
 
     // 保存任务
@@ -555,6 +844,8 @@ fn init_default_config() -> Result<(), String> {
                     rule_config: "".to_string(),
                     save_original: false,
                     format_rules: vec![],
+                    expression: String::new(),
+                    sign: None,
                 },
                 ColumnMapping {
                     id: "2".to_string(),
@@ -568,7 +859,13 @@ fn init_default_config() -> Result<(), String> {
                         rule_type: "pre".to_string(),
                         operation: "DEL_AFTER".to_string(),
                         value: "7".to_string(),
+                        group: None,
+                        replacement: String::new(),
+                        condition: None,
+                        missing_policy: String::new(),
                     }],
+                    expression: String::new(),
+                    sign: None,
                 },
                 ColumnMapping {
                     id: "3".to_string(),
@@ -579,6 +876,8 @@ fn init_default_config() -> Result<(), String> {
                     rule_config: "".to_string(),
                     save_original: false,
                     format_rules: vec![],
+                    expression: String::new(),
+                    sign: None,
                 },
                 ColumnMapping {
                     id: "4".to_string(),
@@ -589,8 +888,12 @@ fn init_default_config() -> Result<(), String> {
                     rule_config: "".to_string(),
                     save_original: false,
                     format_rules: vec![],
+                    expression: String::new(),
+                    sign: None,
                 },
             ],
+            row_filters: vec![],
+            file_options: SourceFileOptions::default(),
         },
         source_b_config: FileTypeConfig {
             header: 1,
@@ -606,6 +909,8 @@ fn init_default_config() -> Result<(), String> {
                     rule_config: "".to_string(),
                     save_original: false,
                     format_rules: vec![],
+                    expression: String::new(),
+                    sign: None,
                 },
                 ColumnMapping {
                     id: "6".to_string(),
@@ -616,6 +921,8 @@ fn init_default_config() -> Result<(), String> {
                     rule_config: "".to_string(),
                     save_original: false,
                     format_rules: vec![],
+                    expression: String::new(),
+                    sign: None,
                 },
                 ColumnMapping {
                     id: "7".to_string(),
@@ -626,6 +933,8 @@ fn init_default_config() -> Result<(), String> {
                     rule_config: "".to_string(),
                     save_original: false,
                     format_rules: vec![],
+                    expression: String::new(),
+                    sign: None,
                 },
                 ColumnMapping {
                     id: "8".to_string(),
@@ -636,8 +945,12 @@ fn init_default_config() -> Result<(), String> {
                     rule_config: "".to_string(),
                     save_original: false,
                     format_rules: vec![],
+                    expression: String::new(),
+                    sign: None,
                 },
             ],
+            row_filters: vec![],
+            file_options: SourceFileOptions::default(),
         },
         match_config: MatchConfig {
             source_a_id_field: "sourceAId".to_string(),
@@ -645,20 +958,38 @@ fn init_default_config() -> Result<(), String> {
                 StatusMapping {
                     source_status: vec!["PAID".to_string(), "COMPLETED".to_string(), "SUCCESS".to_string()],
                     target_status: "PAID".to_string(),
+                    match_type: "EXACT".to_string(),
                 },
                 StatusMapping {
                     source_status: vec!["REJECTED".to_string(), "FAILED".to_string()],
                     target_status: "REJECTED".to_string(),
+                    match_type: "EXACT".to_string(),
                 },
             ],
             source_b_id_field: "sourceBId".to_string(),
             source_b_status_mapping: vec![StatusMapping {
                 source_status: vec!["PAID".to_string(), "SETTLED".to_string()],
                 target_status: "PAID".to_string(),
+                match_type: "EXACT".to_string(),
             }],
+            source_a_key_fields: vec![],
+            source_b_key_fields: vec![],
+            match_passes: vec![],
+            group_match: None,
+            fuzzy_match: None,
+            max_time_diff_hours: 0.0,
+            reconciliation_timezone: DEFAULT_RECONCILIATION_TIMEZONE.to_string(),
             use_historical_source_a: false,
             use_historical_source_b: false,
             history_days: 5,
+            amount_tolerance: 0.0,
+            amount_tolerance_percent: 0.0,
+            amount_currency: "BRL".to_string(),
+            currency_precisions: std::collections::HashMap::new(),
+            currency_conversion: None,
+            fee: None,
+            require_sign_agreement: false,
+            default_status: String::new(),
         },
     };
 
@@ -679,6 +1010,10 @@ fn upload_order_file(
     upload_date: String, // 新增：用于去重的日期 YYYY-MM-DD
     header_row: usize,
     mappings: Vec<ColumnMapping>,
+    timezone: Option<String>, // 数据源时区，默认 UTC
+    reconciliation_timezone: Option<String>, // 对账统一时区，默认 UTC（与匹配配置的默认值一致）
+    file_options: Option<SourceFileOptions>, // Excel 工作表、表尾行数等
+    row_filters: Option<Vec<RowFilter>>, // 行过滤，被排除的行不保存
 ) -> Result<OrderFile, String> {
     log::info!("Uploading order file: file_name={}, config_id={}, source_name={}", 
                file_name, config_id, source_name);
@@ -699,13 +1034,35 @@ fn upload_order_file(
     
     // 加载并清洗数据
     let table_name = "temp_data";
+    let file_options = file_options.unwrap_or_default();
     processor
-        .load_csv_to_table(&file_path, table_name, header_row)
+        .load_file_to_table(&file_path, table_name, header_row, &file_options, &mappings)
         .map_err(|e| format!("加载文件失败: {}", e))?;
     
+    register_lookup_tables(&processor)?;
+
     processor
-        .apply_data_cleaning(table_name, &mappings)
+        .apply_data_cleaning(
+            table_name,
+            &mappings,
+            timezone.as_deref().unwrap_or("UTC"),
+            reconciliation_timezone
+                .as_deref()
+                .unwrap_or(DEFAULT_RECONCILIATION_TIMEZONE),
+            &file_options.decimal_separator,
+        )
         .map_err(|e| format!("清洗数据失败: {}", e))?;
+
+    let excluded = processor
+        .apply_row_filters(
+            table_name,
+            &row_filters.unwrap_or_default(),
+            &file_options.decimal_separator,
+        )
+        .map_err(|e| format!("过滤数据失败: {}", e))?;
+    if !excluded.is_empty() {
+        log::info!("Row filters excluded {} rows from {}", excluded.len(), file_name);
+    }
     
     let records = processor
         .get_table_data(table_name)
@@ -826,6 +1183,7 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .invoke_handler(tauri::generate_handler![
             read_csv_headers,
+            list_excel_sheets,
             reconcile,
             export_results,
             load_configs,
@@ -833,11 +1191,18 @@ pub fn run() {
             delete_config,
             export_config,
             import_config,
+            load_lookup_tables,
+            save_lookup_table,
+            delete_lookup_table,
+            load_fx_rates,
+            save_fx_rates,
+            import_fx_rates,
             load_tasks,
             load_task_result,
             delete_task,
             get_tasks_by_config,
             double_check_task,
+            resolve_suggested_match,
             init_default_config,
             upload_order_file,
             list_order_files,
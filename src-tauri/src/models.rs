@@ -28,6 +28,12 @@ pub struct FileTypeConfig {
     pub timezone: String,
     pub remove_duplicate: bool,
     pub mappings: Vec<ColumnMapping>,
+    // 行过滤：清洗后、去重和匹配前执行，被排除的行单独返回
+    #[serde(default)]
+    pub row_filters: Vec<RowFilter>,
+    // 源文件读取选项（Excel 工作表、表尾行数等）
+    #[serde(default)]
+    pub file_options: SourceFileOptions,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,6 +48,59 @@ pub struct FileConfig {
     pub header: usize,
     pub timezone: String,
     pub remove_duplicate: bool,
+    #[serde(default)]
+    pub row_filters: Vec<RowFilter>,
+    #[serde(default)]
+    pub file_options: SourceFileOptions,
+}
+
+// 源文件读取选项：CSV 和 Excel（.xlsx/.xls）通用
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceFileOptions {
+    // Excel 工作表名称，为空时读取第一个工作表
+    #[serde(default)]
+    pub sheet_name: String,
+    // 忽略文件末尾的行数（合计行、签章说明等）
+    #[serde(default)]
+    pub skip_footer: usize,
+    // 没有表头行（header 为 0）时，数据之前需要跳过的行数；有表头时表头之前的行自动跳过
+    #[serde(default)]
+    pub skip_rows: usize,
+    // CSV 字段分隔符，为空时为逗号；制表符可写作 "\t" 或 "TAB"
+    #[serde(default)]
+    pub delimiter: String,
+    // CSV 引号字符，为空时为双引号
+    #[serde(default)]
+    pub quote: String,
+    // 金额的小数点："." 或 ","（如 1.234,56）；为空时为 "."
+    #[serde(default)]
+    pub decimal_separator: String,
+    // 文件编码（如 UTF-8、GBK、ISO-8859-1、UTF-16），为空时为 UTF-8；带 BOM 的文件按 BOM 识别
+    #[serde(default)]
+    pub encoding: String,
+    // 加载时的列类型覆盖：源列名 -> 类型（见 COLUMN_TYPE_OVERRIDES）
+    // 未覆盖时，映射为 ID、字符串、状态、币种、时间的源列按 VARCHAR 读取，其余列自动识别类型
+    #[serde(default)]
+    pub column_types: HashMap<String, String>,
+}
+
+/// 列类型覆盖可选的类型
+pub const COLUMN_TYPE_OVERRIDES: [&str; 6] = ["VARCHAR", "BIGINT", "DOUBLE", "DATE", "TIMESTAMP", "BOOLEAN"];
+
+impl SourceFileOptions {
+    /// 表头（没有表头时为第一行数据）之前需要跳过的行数，header_row 从 1 开始
+    pub fn leading_rows(&self, header_row: usize) -> usize {
+        if header_row > 0 {
+            header_row - 1
+        } else {
+            self.skip_rows
+        }
+    }
+
+    pub fn uses_decimal_comma(&self) -> bool {
+        self.decimal_separator.trim() == ","
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -62,6 +121,46 @@ pub struct ColumnMapping {
     pub rule_config: String,
     pub save_original: bool,
     pub format_rules: Vec<FormatRule>,
+    // 计算表达式（如 amount - fee、concat(branch, '-', account)），非空时代替 source_column 作为取值
+    #[serde(default)]
+    pub expression: String,
+    // 金额符号规则：借贷分列或借贷标识列，仅对 OrderAmount 字段有意义
+    #[serde(default)]
+    pub sign: Option<AmountSignConfig>,
+}
+
+// 金额符号规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AmountSignConfig {
+    // DEBIT_CREDIT：借方、贷方分两列（代替 source_column）；INDICATOR：无符号金额 + 借贷标识列
+    pub mode: String,
+    #[serde(default)]
+    pub debit_column: String,
+    #[serde(default)]
+    pub credit_column: String,
+    #[serde(default)]
+    pub indicator_column: String,
+    // 标识列中表示借方的值（忽略大小写），为空时使用 D / DR / DEBIT
+    #[serde(default)]
+    pub debit_values: Vec<String>,
+    // 借方记为负数（默认），为 false 时借方记为正数、贷方记为负数
+    #[serde(default = "default_true")]
+    pub debit_negative: bool,
+}
+
+impl AmountSignConfig {
+    pub fn effective_debit_values(&self) -> Vec<String> {
+        if self.debit_values.is_empty() {
+            vec!["D".to_string(), "DR".to_string(), "DEBIT".to_string()]
+        } else {
+            self.debit_values.clone()
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -71,6 +170,61 @@ pub struct FormatRule {
     pub rule_type: String,
     pub operation: String,
     pub value: String,
+    // REGEX_EXTRACT 提取的捕获组序号（默认 1，0 表示整个匹配）
+    #[serde(default)]
+    pub group: Option<usize>,
+    // REGEX_REPLACE 的替换内容，可用 \1 引用捕获组
+    #[serde(default)]
+    pub replacement: String,
+    // 规则生效条件，为空时对所有行生效
+    #[serde(default)]
+    pub condition: Option<RuleCondition>,
+    // LOOKUP 未找到对应值时的处理：KEEP（保留原值，默认）/ NULL（置空）/ FLAG（标记为 UNMAPPED:原值）
+    #[serde(default)]
+    pub missing_policy: String,
+}
+
+// 对照表（字典），供 LOOKUP 规则将原值映射为目标值
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LookupTable {
+    pub id: String,
+    // 表名，LOOKUP 规则的 value 填写该名称
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub entries: Vec<LookupEntry>,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LookupEntry {
+    pub key: String,
+    pub value: String,
+}
+
+// 行过滤条件：INCLUDE 只保留满足条件的行（多个 INCLUDE 需同时满足），EXCLUDE 排除满足条件的行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RowFilter {
+    // 过滤说明（如 "手续费行"），写入被排除行的 excluded_by 列
+    #[serde(default)]
+    pub name: String,
+    // INCLUDE / EXCLUDE
+    pub action: String,
+    pub condition: RuleCondition,
+}
+
+// 格式规则的生效条件：按同一行另一列的值判断
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleCondition {
+    pub column: String,
+    // EQUALS / NOT_EQUALS / IN（value 为逗号分隔列表）/ REGEX / GT / GTE / LT / LTE
+    pub operator: String,
+    pub value: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -78,6 +232,22 @@ pub struct FormatRule {
 pub struct StatusMapping {
     pub source_status: Vec<String>,
     pub target_status: String,
+    // source_status 的匹配方式：EXACT（默认）/ WILDCARD（* ?）/ REGEX / RANGE（数字状态码范围，如 00-09）
+    #[serde(default)]
+    pub match_type: String,
+}
+
+/// 状态映射的匹配优先级：同一状态命中多种映射时，排在前面的匹配方式生效
+pub const STATUS_MATCH_PRECEDENCE: [&str; 4] = ["EXACT", "RANGE", "WILDCARD", "REGEX"];
+
+impl StatusMapping {
+    pub fn effective_match_type(&self) -> &str {
+        if self.match_type.is_empty() {
+            "EXACT"
+        } else {
+            &self.match_type
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -87,6 +257,27 @@ pub struct MatchConfig {
     pub source_a_status_mapping: Vec<StatusMapping>,
     pub source_b_id_field: String,
     pub source_b_status_mapping: Vec<StatusMapping>,
+    // 组合匹配键（有序），为空时使用 source_a_id_field / source_b_id_field
+    #[serde(default)]
+    pub source_a_key_fields: Vec<String>,
+    #[serde(default)]
+    pub source_b_key_fields: Vec<String>,
+    // 多轮级联匹配，按顺序执行，每轮只处理前面轮次未匹配的记录
+    // 为空时使用上面的匹配键执行单轮匹配
+    #[serde(default)]
+    pub match_passes: Vec<MatchPass>,
+    // 分组匹配（一对多/多对一），用于批量结算或拆分付款
+    #[serde(default)]
+    pub group_match: Option<GroupMatchConfig>,
+    // 模糊ID匹配：对剩余单边账按ID相似度给出疑似匹配建议（不自动匹配）
+    #[serde(default)]
+    pub fuzzy_match: Option<FuzzyMatchConfig>,
+    // 匹配时两侧时间（OrderTime 字段，按各自时区换算后）的最大允许差值（小时），0 表示不限制
+    #[serde(default)]
+    pub max_time_diff_hours: f64,
+    // 对账统一时区，各数据源的 OrderTime 字段清洗时换算到该时区
+    #[serde(default = "default_reconciliation_timezone")]
+    pub reconciliation_timezone: String,
     // 是否启用历史数据对账（数据源A）
     #[serde(default)]
     pub use_historical_source_a: bool,
@@ -96,12 +287,165 @@ pub struct MatchConfig {
     // 历史数据范围（天数）
     #[serde(default = "default_history_days")]
     pub history_days: usize,
+    // 金额容差（绝对值），金额差不超过该值视为容差内匹配
+    #[serde(default)]
+    pub amount_tolerance: f64,
+    // 金额容差（百分比，相对数据源B金额），如 0.5 表示 0.5%
+    #[serde(default)]
+    pub amount_tolerance_percent: f64,
+    // 对账金额的币种（ISO 4217，如 BRL），决定金额保留的小数位数
+    #[serde(default)]
+    pub amount_currency: String,
+    // 币种小数位数覆盖配置，如 {"JPY": 0, "KWD": 3}；未配置的币种使用内置值
+    #[serde(default)]
+    pub currency_precisions: HashMap<String, u32>,
+    // 币种换算：比较前将一侧金额按汇率表换算为 amount_currency
+    #[serde(default)]
+    pub currency_conversion: Option<CurrencyConversionConfig>,
+    // 手续费：一侧为扣费后的净额时，先还原为总额再与另一侧比较
+    #[serde(default)]
+    pub fee: Option<FeeConfig>,
+    // 要求两侧金额符号一致才能匹配（退款不会与付款匹配）
+    #[serde(default)]
+    pub require_sign_agreement: bool,
+    // 未被状态映射覆盖的状态使用的默认标准状态，为空时保持为空（归入状态不一致）
+    #[serde(default)]
+    pub default_status: String,
+}
+
+// 级联匹配中的一轮
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchPass {
+    // 轮次名称，会写入结果的 match_pass 列
+    pub name: String,
+    pub source_a_key_fields: Vec<String>,
+    pub source_b_key_fields: Vec<String>,
+    // 本轮的时间窗口（小时），为空时使用 MatchConfig.max_time_diff_hours
+    #[serde(default)]
+    pub max_time_diff_hours: Option<f64>,
+}
+
+// 分组匹配配置：按关联字段（批次号、日期等）分组后比较金额合计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupMatchConfig {
+    pub enabled: bool,
+    pub source_a_group_field: String,
+    pub source_b_group_field: String,
+}
+
+// 模糊ID匹配配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FuzzyMatchConfig {
+    pub enabled: bool,
+    // 相似度算法：JARO_WINKLER / LEVENSHTEIN
+    pub algorithm: String,
+    // 相似度阈值（0~1），达到阈值才作为疑似匹配
+    pub threshold: f64,
+}
+
+// 币种换算配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CurrencyConversionConfig {
+    pub enabled: bool,
+    // 需要换算的一侧：A / B
+    pub convert_side: String,
+    // 该侧未配置 Currency 字段或币种为空时使用的默认币种
+    #[serde(default)]
+    pub source_currency: String,
+}
+
+// 手续费配置：净额侧优先使用 OrderFee 字段，未映射时按 固定费用 + 总额 × 费率% 计算
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeConfig {
+    pub enabled: bool,
+    // 金额为净额的一侧：A / B
+    pub net_side: String,
+    // 每笔固定费用（对账币种）
+    #[serde(default)]
+    pub fixed_fee: f64,
+    // 按总额计算的费率（百分比），如 1.5 表示 1.5%
+    #[serde(default)]
+    pub percent_fee: f64,
+}
+
+// 汇率：rate_date 当日起 1 个 base_currency 兑换 rate 个 quote_currency
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FxRate {
+    // 日期 YYYY-MM-DD
+    pub rate_date: String,
+    pub base_currency: String,
+    pub quote_currency: String,
+    // 汇率以字符串保存，避免精度损失
+    pub rate: String,
 }
 
 fn default_history_days() -> usize {
     5
 }
 
+/// 金额列的最大小数位数（与清洗后的 DECIMAL(18,4) 列一致）
+pub const MAX_AMOUNT_SCALE: u32 = 4;
+
+/// 未配置对账统一时区时使用的时区
+pub const DEFAULT_RECONCILIATION_TIMEZONE: &str = "UTC";
+
+fn default_reconciliation_timezone() -> String {
+    DEFAULT_RECONCILIATION_TIMEZONE.to_string()
+}
+
+impl MatchConfig {
+    /// 数据源A的匹配键字段列表（未配置组合键时退化为单一ID字段）
+    pub fn source_a_keys(&self) -> Vec<String> {
+        if self.source_a_key_fields.is_empty() {
+            vec![self.source_a_id_field.clone()]
+        } else {
+            self.source_a_key_fields.clone()
+        }
+    }
+
+    /// 数据源B的匹配键字段列表（未配置组合键时退化为单一ID字段）
+    pub fn source_b_keys(&self) -> Vec<String> {
+        if self.source_b_key_fields.is_empty() {
+            vec![self.source_b_id_field.clone()]
+        } else {
+            self.source_b_key_fields.clone()
+        }
+    }
+
+    /// 对账金额保留的小数位数：优先使用币种覆盖配置，其次为币种的标准最小单位，默认 2 位
+    pub fn amount_scale(&self) -> u32 {
+        let currency = self.amount_currency.trim().to_uppercase();
+        if let Some(scale) = self.currency_precisions.get(&currency) {
+            return (*scale).min(MAX_AMOUNT_SCALE);
+        }
+        match currency.as_str() {
+            "JPY" | "KRW" | "VND" | "CLP" | "ISK" | "PYG" | "UGX" | "XAF" | "XOF" => 0,
+            "BHD" | "IQD" | "JOD" | "KWD" | "LYD" | "OMR" | "TND" => 3,
+            _ => 2,
+        }
+    }
+
+    /// 实际执行的匹配轮次（未配置多轮匹配时只有一轮默认匹配）
+    pub fn effective_match_passes(&self) -> Vec<MatchPass> {
+        if self.match_passes.is_empty() {
+            vec![MatchPass {
+                name: "DEFAULT".to_string(),
+                source_a_key_fields: self.source_a_keys(),
+                source_b_key_fields: self.source_b_keys(),
+                max_time_diff_hours: None,
+            }]
+        } else {
+            self.match_passes.clone()
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ReconciliationResult {
@@ -109,6 +453,23 @@ pub struct ReconciliationResult {
     pub only_in_a: Vec<HashMap<String, serde_json::Value>>,
     pub only_in_b: Vec<HashMap<String, serde_json::Value>>,
     pub diff_amount: Vec<HashMap<String, serde_json::Value>>,
+    // 金额不完全相等但在容差范围内的记录
+    #[serde(default)]
+    pub matched_with_tolerance: Vec<HashMap<String, serde_json::Value>>,
+    // ID 匹配成功但标准化状态不一致的记录（如 PAID vs REJECTED）
+    #[serde(default)]
+    pub status_mismatch: Vec<HashMap<String, serde_json::Value>>,
+    // 分组匹配结果，每行代表一组（含两侧成员ID）
+    #[serde(default)]
+    pub grouped_matched: Vec<HashMap<String, serde_json::Value>>,
+    // 疑似匹配建议（带相似度 match_score），对应记录仍保留在单边账中，需人工确认
+    #[serde(default)]
+    pub suggested_matches: Vec<HashMap<String, serde_json::Value>>,
+    // 被行过滤排除、未参与对账的记录（带排除原因 excluded_by）
+    #[serde(default)]
+    pub excluded_a: Vec<HashMap<String, serde_json::Value>>,
+    #[serde(default)]
+    pub excluded_b: Vec<HashMap<String, serde_json::Value>>,
 }
 
 // 对账任务记录
@@ -131,6 +492,19 @@ pub struct ReconciliationTask {
     // 是否使用了历史数据
     pub used_historical_source_a: bool,
     pub used_historical_source_b: bool,
+    // 本次对账中未被状态映射覆盖的原始状态
+    #[serde(default)]
+    pub unmapped_statuses: Vec<UnmappedStatus>,
+}
+
+// 未映射的原始状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnmappedStatus {
+    // 数据源名称
+    pub source_name: String,
+    pub raw_status: String,
+    pub count: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -140,6 +514,18 @@ pub struct ReconciliationStats {
     pub only_in_source_a_count: usize,
     pub only_in_source_b_count: usize,
     pub diff_amount_count: usize,
+    #[serde(default)]
+    pub tolerance_matched_count: usize,
+    #[serde(default)]
+    pub status_mismatch_count: usize,
+    #[serde(default)]
+    pub grouped_matched_count: usize,
+    #[serde(default)]
+    pub suggested_match_count: usize,
+    #[serde(default)]
+    pub excluded_source_a_count: usize,
+    #[serde(default)]
+    pub excluded_source_b_count: usize,
     pub total_source_a: usize,
     pub total_source_b: usize,
 }
@@ -300,7 +300,7 @@ impl OrderManager {
                         }
                         "gt" => {
                             if let (Some(v1), Ok(v2)) =
-                                (value.as_f64(), condition.value.parse::<f64>())
+                                (numeric_value(value), condition.value.parse::<f64>())
                             {
                                 v1 > v2
                             } else {
@@ -309,7 +309,7 @@ impl OrderManager {
                         }
                         "lt" => {
                             if let (Some(v1), Ok(v2)) =
-                                (value.as_f64(), condition.value.parse::<f64>())
+                                (numeric_value(value), condition.value.parse::<f64>())
                             {
                                 v1 < v2
                             } else {
@@ -317,7 +317,7 @@ impl OrderManager {
                             }
                         }
                         "between" => {
-                            if let Some(v1) = value.as_f64() {
+                            if let Some(v1) = numeric_value(value) {
                                 if let Ok(v2) = condition.value.parse::<f64>() {
                                     if let Some(v3_str) = &condition.value2 {
                                         if let Ok(v3) = v3_str.parse::<f64>() {
@@ -445,3 +445,10 @@ impl OrderManager {
     }
 
 }
+
+/// 过滤条件中的数值：金额以精确小数字符串保存，也需要参与比较
+fn numeric_value(value: &serde_json::Value) -> Option<f64> {
+    value
+        .as_f64()
+        .or_else(|| value.as_str().and_then(|s| s.trim().parse::<f64>().ok()))
+}
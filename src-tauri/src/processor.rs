@@ -1,12 +1,48 @@
 use crate::models::*;
+use crate::camt_parser;
+use crate::csv_reader;
+use crate::excel_reader;
+use crate::expression;
+use crate::time_parser;
 use anyhow::{Context, Result};
 use duckdb::Connection;
 use std::collections::HashMap;
+use std::str::FromStr;
 
 pub struct DataProcessor {
     conn: Connection,
 }
 
+/// 对账时单侧数据源用到的字段（来自字段映射）及时间字段所在时区
+#[derive(Debug, Clone, Default)]
+pub struct SourceFields {
+    pub amount_field: String,
+    pub time_field: String,
+    pub currency_field: String,
+    pub fee_field: String,
+    pub timezone: String,
+}
+
+impl SourceFields {
+    pub fn from_mappings(mappings: &[ColumnMapping], timezone: &str) -> Self {
+        let field_of = |field_type: &str| {
+            mappings
+                .iter()
+                .find(|m| m.field_type == field_type)
+                .map(|m| m.field_name.clone())
+                .unwrap_or_default()
+        };
+
+        Self {
+            amount_field: field_of("OrderAmount"),
+            time_field: field_of("OrderTime"),
+            currency_field: field_of("Currency"),
+            fee_field: field_of("OrderFee"),
+            timezone: timezone.to_string(),
+        }
+    }
+}
+
 impl DataProcessor {
     pub fn new() -> Result<Self> {
         let conn = Connection::open_in_memory()?;
@@ -21,45 +57,164 @@ impl DataProcessor {
         Ok(())
     }
 
-    pub fn read_csv_headers(&self, file_path: &str, header_row: usize) -> Result<Vec<String>> {
-        let mut reader = csv::ReaderBuilder::new()
-            .has_headers(header_row > 0)
-            .from_path(file_path)
-            .context("无法打开CSV文件")?;
-
-        // Skip to header row if needed
-        let mut iter = reader.records();
-        for _ in 0..header_row.saturating_sub(1) {
-            iter.next();
+    pub fn read_csv_headers(
+        &self,
+        file_path: &str,
+        header_row: usize,
+        options: &SourceFileOptions,
+    ) -> Result<Vec<String>> {
+        if excel_reader::is_excel_file(file_path) {
+            return excel_reader::read_headers(file_path, options, header_row);
+        }
+        if camt_parser::is_camt_file(file_path) {
+            // camt 对账单展开后的列固定，解析一遍以确认文件格式正确
+            camt_parser::parse_file(file_path)?;
+            return Ok(camt_parser::CAMT_COLUMNS.iter().map(|c| c.to_string()).collect());
         }
 
-        let headers = reader.headers()?;
-        let header_vec: Vec<String> = headers.iter().map(|h| h.to_string()).collect();
-        
-        Ok(header_vec)
+        csv_reader::read_headers(file_path, options, header_row)
     }
 
+    /// 按文件读取选项加载 CSV：非 UTF-8 文件先转码并去掉表尾，跳过表头之前的行，使用配置的分隔符和引号；
+    /// column_types 中的列按指定类型读取，其余列自动识别类型
     pub fn load_csv_to_table(
         &self,
         file_path: &str,
         table_name: &str,
         header_row: usize,
+        options: &SourceFileOptions,
+        column_types: &[(String, String)],
     ) -> Result<()> {
+        let delimiter = csv_reader::delimiter(options)? as char;
+        let quote = csv_reader::quote(options)? as char;
+        let utf8_file = csv_reader::to_utf8_file(file_path, options)?;
+        let path = utf8_file
+            .as_ref()
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|| file_path.to_string());
+
         // Create table and load CSV using DuckDB's native CSV reader
         let sql = format!(
-            "CREATE TABLE {} AS SELECT * FROM read_csv_auto('{}', header={}, delim=',', quote='\"')",
-            table_name, file_path, header_row > 0
+            "CREATE TABLE {} AS SELECT * FROM read_csv_auto('{}', header={}, skip={}, delim={}, quote={}{})",
+            table_name,
+            path,
+            header_row > 0,
+            options.leading_rows(header_row),
+            quote_literal(&delimiter.to_string()),
+            quote_literal(&quote.to_string()),
+            Self::column_types_option(column_types)
         );
-        
-        self.conn.execute(&sql, [])?;
+
+        let result = self.conn.execute(&sql, []);
+        if let Some(temp_file) = &utf8_file {
+            let _ = std::fs::remove_file(temp_file);
+        }
+        result?;
         Ok(())
     }
 
-    /// 将历史数据加载到现有表中
+    /// 加载数据源文件：Excel、camt XML 对账单先转换为临时 CSV，CSV 直接加载；文件末尾的合计行等在读取前去掉
+    /// 映射为 ID、字符串、状态、币种、时间的源列按文本读取，避免自动识别类型时丢失前导零、变成科学计数法或猜错日期格式；
+    /// Excel、camt 文件和小数点为逗号的 CSV 所有列都按文本读取（金额在清洗时再转换）；fileOptions.columnTypes 可覆盖单列类型
+    pub fn load_file_to_table(
+        &self,
+        file_path: &str,
+        table_name: &str,
+        header_row: usize,
+        options: &SourceFileOptions,
+        mappings: &[ColumnMapping],
+    ) -> Result<()> {
+        let is_excel = excel_reader::is_excel_file(file_path);
+        let is_camt = camt_parser::is_camt_file(file_path);
+        let headers = self.read_csv_headers(file_path, header_row, options)?;
+        let text_columns = Self::text_columns(mappings);
+        let all_text = is_excel || is_camt || options.uses_decimal_comma();
+
+        let mut column_types = Vec::new();
+        for header in &headers {
+            let column_type = match options.column_types.get(header) {
+                Some(column_type) => column_type.trim().to_uppercase(),
+                None if all_text || text_columns.contains(header) => "VARCHAR".to_string(),
+                None => continue,
+            };
+            column_types.push((header.clone(), column_type));
+        }
+        if let Some(missing) = options.column_types.keys().find(|c| !headers.contains(c)) {
+            anyhow::bail!("类型覆盖的列 '{}' 不存在。可用列: {}", missing, headers.join(", "));
+        }
+
+        if is_excel || is_camt {
+            // camt 对账单转换后的 CSV 总是带列名
+            let csv_path = if is_excel {
+                excel_reader::convert_to_csv(file_path, options, header_row)?
+            } else {
+                camt_parser::convert_to_csv(file_path)?
+            };
+            let sql = format!(
+                "CREATE TABLE {} AS SELECT * FROM read_csv('{}', header={}, delim=',', quote='\"'{})",
+                table_name,
+                csv_path.display(),
+                is_camt || header_row > 0,
+                Self::column_types_option(&column_types)
+            );
+            let result = self.conn.execute(&sql, []);
+            let _ = std::fs::remove_file(&csv_path);
+            result?;
+            return Ok(());
+        }
+
+        self.load_csv_to_table(file_path, table_name, header_row, options, &column_types)
+    }
+
+    /// 需要按文本读取的源列：所有字段的源列及其表达式引用的列，以及借贷标识列、借贷分列
+    fn text_columns(mappings: &[ColumnMapping]) -> Vec<String> {
+        let mut columns = Vec::new();
+        for mapping in mappings {
+            if let Some(sign) = &mapping.sign {
+                if sign.mode == "INDICATOR" {
+                    columns.push(sign.indicator_column.clone());
+                } else {
+                    columns.push(sign.debit_column.clone());
+                    columns.push(sign.credit_column.clone());
+                }
+            }
+            // 时间列也按文本读取，否则 DuckDB 会按自己猜测的日/月顺序解析 03/04/2024，配置的日期格式不再生效；
+            // 金额、手续费列同样按文本读取，否则会被自动识别为 DOUBLE，转换为定点小数前已丢失精度
+            let is_text = matches!(
+                mapping.field_type.as_str(),
+                "OrderString" | "OrderStatus" | "Currency" | "OrderTime" | "OrderAmount" | "OrderFee"
+            ) || mapping.format_rules.iter().any(|r| r.operation == "DATE_PARSE")
+                || !Self::date_patterns(mapping).is_empty();
+            if !is_text {
+                continue;
+            }
+            if mapping.expression.trim().is_empty() {
+                columns.push(mapping.source_column.clone());
+            } else if let Ok(expr) = expression::parse(&mapping.expression) {
+                columns.extend(expr.columns().into_iter().map(|c| c.to_string()));
+            }
+        }
+        columns
+    }
+
+    /// read_csv 的 types 参数
+    fn column_types_option(column_types: &[(String, String)]) -> String {
+        if column_types.is_empty() {
+            return String::new();
+        }
+        let entries: Vec<String> = column_types
+            .iter()
+            .map(|(column, column_type)| format!("{}: {}", quote_literal(column), quote_literal(column_type)))
+            .collect();
+        format!(", types={{{}}}", entries.join(", "))
+    }
+
+    /// 将历史数据加载到现有表中；新建表时按字段映射的类型建列，与清洗后的数据结构一致
     pub fn load_historical_data(
         &self,
         table_name: &str,
         historical_records: Vec<HashMap<String, serde_json::Value>>,
+        mappings: &[ColumnMapping],
     ) -> Result<()> {
         if historical_records.is_empty() {
             return Ok(());
@@ -83,8 +238,13 @@ impl DataProcessor {
             let column_defs: Vec<String> = columns
                 .iter()
                 .map(|col| {
-                    // 简化处理：所有字段都使用 VARCHAR 类型
-                    format!("{} VARCHAR", col)
+                    // 映射字段使用对应类型，其余字段使用 VARCHAR
+                    let column_type = mappings
+                        .iter()
+                        .find(|m| format!("\"{}\"", m.field_name) == *col)
+                        .map(|m| Self::column_type(&m.field_type))
+                        .unwrap_or("VARCHAR");
+                    format!("{} {}", col, column_type)
                 })
                 .collect();
             
@@ -96,19 +256,33 @@ impl DataProcessor {
             self.conn.execute(&create_sql, [])?;
         }
 
-        // 将历史数据插入到表中
+        // 将历史数据按目标列类型插入：早期保存的历史数据中金额、时间可能还是原始格式的字符串，
+        // 无法转换的值置为空并记录警告，不会让整行插入失败
+        let mut column_types = self.get_table_column_types(table_name)?;
+        let mut unconvertible: HashMap<String, (usize, String)> = HashMap::new();
+        let mut failed_rows = 0;
         for record in historical_records {
-            let columns: Vec<String> = record.keys().map(|k| format!("\"{}\"", k)).collect();
-            let values: Vec<String> = record
-                .values()
-                .map(|v| match v {
-                    serde_json::Value::String(s) => format!("'{}'", s.replace("'", "''")),
-                    serde_json::Value::Number(n) => n.to_string(),
-                    serde_json::Value::Bool(b) => b.to_string(),
-                    serde_json::Value::Null => "NULL".to_string(),
-                    _ => "NULL".to_string(),
-                })
-                .collect();
+            let mut columns = Vec::with_capacity(record.len());
+            let mut values = Vec::with_capacity(record.len());
+            for (column, value) in &record {
+                // 表中没有的列（如配置调整后新增的字段）补充为 VARCHAR 列
+                if !column_types.contains_key(column) {
+                    self.conn.execute(
+                        &format!("ALTER TABLE {} ADD COLUMN IF NOT EXISTS \"{}\" VARCHAR", table_name, column),
+                        [],
+                    )?;
+                    column_types.insert(column.clone(), "VARCHAR".to_string());
+                }
+                let (sql, converted) = Self::historical_value_sql(value, &column_types[column]);
+                if !converted {
+                    let entry = unconvertible
+                        .entry(column.clone())
+                        .or_insert((0, value.to_string()));
+                    entry.0 += 1;
+                }
+                columns.push(format!("\"{}\"", column));
+                values.push(sql);
+            }
 
             let insert_sql = format!(
                 "INSERT INTO {} ({}) VALUES ({})",
@@ -117,12 +291,62 @@ impl DataProcessor {
                 values.join(", ")
             );
 
-            let _ = self.conn.execute(&insert_sql, []);
+            if let Err(e) = self.conn.execute(&insert_sql, []) {
+                failed_rows += 1;
+                log::warn!("历史数据写入 {} 失败: {}", table_name, e);
+            }
+        }
+
+        for (column, (count, example)) in &unconvertible {
+            log::warn!(
+                "历史数据 {} 的字段 '{}' 有 {} 个值无法转换为 {}，已置为空（如 {}）",
+                table_name,
+                column,
+                count,
+                column_types[column],
+                example
+            );
+        }
+        if failed_rows > 0 {
+            log::warn!("历史数据 {} 共 {} 行写入失败", table_name, failed_rows);
         }
 
         Ok(())
     }
 
+    /// 历史数据的值转换为 SQL：非 VARCHAR 列使用 TRY_CAST，时间先按常见格式解析
+    /// 返回 (SQL, 是否能转换为列类型)
+    fn historical_value_sql(value: &serde_json::Value, column_type: &str) -> (String, bool) {
+        let text = match value {
+            serde_json::Value::Null => return ("NULL".to_string(), true),
+            serde_json::Value::String(s) if s.trim().is_empty() && column_type != "VARCHAR" => {
+                return ("NULL".to_string(), true)
+            }
+            serde_json::Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        if column_type == "VARCHAR" {
+            return (quote_literal(&text), true);
+        }
+
+        let (text, converted) = if column_type.starts_with("TIMESTAMP") {
+            match time_parser::parse_local_time(&text) {
+                Some(time) => (time.format(time_parser::TIME_FORMAT).to_string(), true),
+                None => (text, false),
+            }
+        } else if column_type.starts_with("DECIMAL") {
+            let converted = rust_decimal::Decimal::from_str(text.trim()).is_ok()
+                || rust_decimal::Decimal::from_scientific(text.trim()).is_ok();
+            (text, converted)
+        } else {
+            (text, true)
+        };
+        (
+            format!("TRY_CAST({} AS {})", quote_literal(text.trim()), column_type),
+            converted,
+        )
+    }
+
     /// 获取表中的所有数据
     pub fn get_table_data(&self, table_name: &str) -> Result<Vec<HashMap<String, serde_json::Value>>> {
         let sql = format!("SELECT * FROM {}", table_name);
@@ -145,17 +369,72 @@ impl DataProcessor {
         Ok(columns)
     }
 
+    /// 获取表中各列的类型
+    fn get_table_column_types(&self, table_name: &str) -> Result<HashMap<String, String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT column_name, data_type FROM information_schema.columns WHERE table_name = ?",
+        )?;
+        let column_types = stmt
+            .query_map([table_name], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(column_types)
+    }
+
+    /// 注册对照表数据，供 LOOKUP 规则查询
+    pub fn register_lookup_tables(&self, tables: &[LookupTable]) -> Result<()> {
+        self.conn.execute(
+            "CREATE OR REPLACE TEMP TABLE lookup_entries \
+             (table_name VARCHAR, lookup_key VARCHAR, lookup_value VARCHAR)",
+            [],
+        )?;
+        let mut stmt = self
+            .conn
+            .prepare("INSERT INTO lookup_entries VALUES (?, ?, ?)")?;
+        for table in tables {
+            for entry in &table.entries {
+                stmt.execute([table.name.as_str(), entry.key.trim(), entry.value.as_str()])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 按字段映射清洗数据；OrderTime 字段会从数据源时区换算到对账统一时区
+    /// 数据源小数点为逗号时，金额、手续费字段的源列先转换为以点为小数点的数字文本
     pub fn apply_data_cleaning(
         &self,
         table_name: &str,
         mappings: &[ColumnMapping],
+        source_timezone: &str,
+        target_timezone: &str,
+        decimal_separator: &str,
     ) -> Result<()> {
         for mapping in mappings {
-            let mut column_expr = format!("\"{}\"", mapping.source_column);
+            let decimal_comma = decimal_separator.trim() == ","
+                && matches!(mapping.field_type.as_str(), "OrderAmount" | "OrderFee");
+            // 表达式的算术运算、数值条件中引用的文本列同样按逗号小数点转换
+            let decimal_comma_columns = self.decimal_comma_columns(table_name, decimal_separator)?;
+            // 配置了计算表达式时从表达式取值，可引用原始列和前面已清洗的字段；
+            // 借贷分列的金额由贷方减借方得到带符号金额
+            let debit_credit = mapping.sign.as_ref().filter(|sign| sign.mode == "DEBIT_CREDIT");
+            let mut column_expr = if !mapping.expression.trim().is_empty() {
+                let columns = self.get_table_columns(table_name)?;
+                expression::compile(&mapping.expression, &columns, &decimal_comma_columns, decimal_comma)
+                    .with_context(|| format!("字段 '{}' 的表达式无效", mapping.field_name))?
+            } else if let Some(sign) = debit_credit {
+                Self::debit_credit_expr(sign, decimal_comma)
+            } else {
+                Self::source_column_expr(&mapping.source_column, decimal_comma)
+            };
             
             // Apply format rules
             for rule in &mapping.format_rules {
-                column_expr = self.apply_format_rule(&column_expr, rule)?;
+                column_expr = self.apply_format_rule(&column_expr, rule, &decimal_comma_columns)?;
+            }
+
+            // 借贷标识列决定无符号金额的正负
+            if let Some(sign) = mapping.sign.as_ref().filter(|sign| sign.mode == "INDICATOR") {
+                column_expr = Self::indicator_sign_expr(&column_expr, sign);
             }
             
             // Add new column with cleaned data
@@ -170,12 +449,553 @@ impl DataProcessor {
                 table_name, mapping.field_name, column_expr
             );
             self.conn.execute(&update_sql, [])?;
+
+            // 时间解析在 SQL 规则之后进行；非 OrderTime 字段使用 DATE_PARSE 时只统一格式，不换算时区
+            let date_patterns = Self::date_patterns(mapping);
+            if mapping.field_type == "OrderTime" || !date_patterns.is_empty() {
+                let target_timezone = if mapping.field_type == "OrderTime" {
+                    target_timezone
+                } else {
+                    source_timezone
+                };
+                self.convert_time_column(
+                    table_name,
+                    &mapping.field_name,
+                    &mapping.rule_type,
+                    &date_patterns,
+                    source_timezone,
+                    target_timezone,
+                )
+                .with_context(|| format!("时间字段 '{}' 换算失败", mapping.field_name))?;
+            }
+
+            self.convert_column_type(table_name, &mapping.field_name, &mapping.field_type)
+                .with_context(|| format!("字段 '{}' 类型转换失败", mapping.field_name))?;
         }
         
         Ok(())
     }
 
-    fn apply_format_rule(&self, column_expr: &str, rule: &FormatRule) -> Result<String> {
+    /// 按汇率表将一侧金额换算为对账币种：新增 <金额字段>Converted 与 fxRate 列，
+    /// 并将该侧的比较金额字段切换为换算后的列。原金额列保持不变
+    pub fn apply_currency_conversion(
+        &self,
+        match_config: &MatchConfig,
+        source_a: &mut SourceFields,
+        source_b: &mut SourceFields,
+        rates: &[FxRate],
+    ) -> Result<()> {
+        let conversion = match &match_config.currency_conversion {
+            Some(conversion) if conversion.enabled => conversion,
+            _ => return Ok(()),
+        };
+        let target_currency = match_config.amount_currency.trim().to_uppercase();
+        if target_currency.is_empty() {
+            anyhow::bail!("启用币种换算时必须配置对账币种 amountCurrency");
+        }
+        let (table_name, fields) = match conversion.convert_side.as_str() {
+            "A" => ("source_a", source_a),
+            "B" => ("source_b", source_b),
+            other => anyhow::bail!("不支持的换算数据源: {}（应为 A 或 B）", other),
+        };
+        if fields.amount_field.is_empty() {
+            anyhow::bail!("数据源{}未配置金额字段，无法进行币种换算", conversion.convert_side);
+        }
+
+        self.load_fx_rates(rates, &target_currency)?;
+
+        // 每条记录的币种与取汇率的日期：无时间时使用最新汇率
+        let default_currency = quote_literal(&conversion.source_currency.trim().to_uppercase());
+        let currency_expr = if fields.currency_field.is_empty() {
+            format!("NULLIF({}, '')", default_currency)
+        } else {
+            format!(
+                "COALESCE(NULLIF(UPPER(TRIM(CAST(t.\"{}\" AS VARCHAR))), ''), NULLIF({}, ''))",
+                fields.currency_field, default_currency
+            )
+        };
+        let date_expr = if fields.time_field.is_empty() {
+            "DATE '9999-12-31'".to_string()
+        } else {
+            format!("COALESCE(CAST(t.\"{}\" AS DATE), DATE '9999-12-31')", fields.time_field)
+        };
+        self.conn.execute(
+            &format!(
+                "CREATE OR REPLACE TEMP TABLE fx_conversion AS \
+                 SELECT c.row_id, c.currency, c.rate_day, r.rate \
+                 FROM (SELECT t.rowid AS row_id, {} AS currency, {} AS rate_day FROM {} t) c \
+                 ASOF LEFT JOIN fx_lookup r ON c.currency = r.currency AND c.rate_day >= r.rate_date",
+                currency_expr, date_expr, table_name
+            ),
+            [],
+        )?;
+
+        // 缺少币种或汇率时不做猜测，直接报错
+        let missing: Vec<String> = {
+            let sql = format!(
+                "SELECT COALESCE(currency, '(空)') || ' → ' || {} || '（' || CAST(MIN(rate_day) AS VARCHAR) || ' 起）' \
+                 FROM fx_conversion WHERE currency IS NULL OR (currency <> {} AND rate IS NULL) \
+                 GROUP BY currency ORDER BY 1 LIMIT 10",
+                quote_literal(&target_currency),
+                quote_literal(&target_currency)
+            );
+            let mut stmt = self.conn.prepare(&sql)?;
+            let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+            rows.filter_map(|r| r.ok()).collect()
+        };
+        if !missing.is_empty() {
+            anyhow::bail!(
+                "数据源{}缺少可用汇率: {}",
+                conversion.convert_side,
+                missing.join(", ")
+            );
+        }
+
+        let converted_field = format!("{}Converted", fields.amount_field);
+        self.conn.execute(
+            &format!(
+                "ALTER TABLE {} ADD COLUMN IF NOT EXISTS \"{}\" DECIMAL(18,{})",
+                table_name, converted_field, MAX_AMOUNT_SCALE
+            ),
+            [],
+        )?;
+        self.conn.execute(
+            &format!(
+                "ALTER TABLE {} ADD COLUMN IF NOT EXISTS \"fxRate\" DECIMAL(28,10)",
+                table_name
+            ),
+            [],
+        )?;
+        // 汇率（含预先算好的反向汇率）均为 DECIMAL，直接相乘后取整到金额精度，不经过 DOUBLE
+        let update_sql = format!(
+            "UPDATE {table} SET \
+                \"{converted}\" = CASE \
+                    WHEN c.currency = {target} THEN {table}.\"{amount}\" \
+                    ELSE CAST({table}.\"{amount}\" * c.rate AS DECIMAL(18,{scale})) END, \
+                \"fxRate\" = CASE \
+                    WHEN c.currency = {target} THEN 1 \
+                    ELSE CAST(c.rate AS DECIMAL(28,10)) END \
+             FROM fx_conversion c WHERE {table}.rowid = c.row_id",
+            table = table_name,
+            converted = converted_field,
+            amount = fields.amount_field,
+            target = quote_literal(&target_currency),
+            scale = MAX_AMOUNT_SCALE,
+        );
+        self.conn.execute(&update_sql, [])?;
+
+        fields.amount_field = converted_field;
+        Ok(())
+    }
+
+    /// 净额侧还原为总额：新增 feeExpected（预期手续费）与 <金额字段>Gross 列，
+    /// 并将该侧的比较金额字段切换为总额列。手续费优先取 OrderFee 字段，
+    /// 未映射时按公式 总额 = (净额 + 固定费用) / (1 - 费率%) 反推
+    pub fn apply_fee_adjustment(
+        &self,
+        match_config: &MatchConfig,
+        source_a: &mut SourceFields,
+        source_b: &mut SourceFields,
+    ) -> Result<()> {
+        let fee = match &match_config.fee {
+            Some(fee) if fee.enabled => fee,
+            _ => return Ok(()),
+        };
+        let (table_name, fields) = match fee.net_side.as_str() {
+            "A" => ("source_a", source_a),
+            "B" => ("source_b", source_b),
+            other => anyhow::bail!("不支持的净额数据源: {}（应为 A 或 B）", other),
+        };
+        if fields.amount_field.is_empty() {
+            anyhow::bail!("数据源{}未配置金额字段，无法计算手续费", fee.net_side);
+        }
+        if !(0.0..100.0).contains(&fee.percent_fee) {
+            anyhow::bail!("手续费率必须在 0 到 100 之间: {}", fee.percent_fee);
+        }
+
+        let net = format!("\"{}\"", fields.amount_field);
+        let gross_expr = if !fields.fee_field.is_empty() {
+            format!("{} + COALESCE(\"{}\", 0)", net, fields.fee_field)
+        } else if fee.percent_fee == 0.0 {
+            format!("{} + {}", net, fee.fixed_fee)
+        } else {
+            format!("({} + {}) / (1 - {} / 100)", net, fee.fixed_fee, fee.percent_fee)
+        };
+
+        let gross_field = format!("{}Gross", fields.amount_field);
+        for column in [gross_field.as_str(), "feeExpected"] {
+            self.conn.execute(
+                &format!(
+                    "ALTER TABLE {} ADD COLUMN IF NOT EXISTS \"{}\" DECIMAL(18,{})",
+                    table_name, column, MAX_AMOUNT_SCALE
+                ),
+                [],
+            )?;
+        }
+        self.conn.execute(
+            &format!(
+                "UPDATE {table} SET \"{gross}\" = CAST({expr} AS DECIMAL(18,{scale}))",
+                table = table_name,
+                gross = gross_field,
+                expr = gross_expr,
+                scale = MAX_AMOUNT_SCALE
+            ),
+            [],
+        )?;
+        self.conn.execute(
+            &format!(
+                "UPDATE {} SET \"feeExpected\" = \"{}\" - {}",
+                table_name, gross_field, net
+            ),
+            [],
+        )?;
+
+        fields.amount_field = gross_field;
+        Ok(())
+    }
+
+    /// 加载汇率表，生成换算到目标币种的查找表 fx_lookup(rate_date, currency, rate)
+    /// 目标币种为报价币种时直接使用汇率，为基础币种时使用反向汇率；
+    /// 反向汇率在 Rust 中按 20 位小数精确计算，避免 DuckDB 的小数除法退化为 DOUBLE
+    fn load_fx_rates(&self, rates: &[FxRate], target_currency: &str) -> Result<()> {
+        self.conn.execute(
+            "CREATE OR REPLACE TEMP TABLE fx_rates \
+             (rate_date DATE, base_currency VARCHAR, quote_currency VARCHAR, \
+              rate DECIMAL(28,10), inverse_rate DECIMAL(38,20))",
+            [],
+        )?;
+        {
+            let mut stmt = self.conn.prepare(
+                "INSERT INTO fx_rates VALUES \
+                 (CAST(? AS DATE), ?, ?, CAST(? AS DECIMAL(28,10)), CAST(? AS DECIMAL(38,20)))",
+            )?;
+            for rate in rates {
+                let inverse = rust_decimal::Decimal::from_str(rate.rate.trim())
+                    .ok()
+                    .and_then(|value| rust_decimal::Decimal::ONE.checked_div(value))
+                    .map(|value| value.round_dp(20).to_string())
+                    .with_context(|| {
+                        format!(
+                            "汇率无效: {} {}/{} = {}",
+                            rate.rate_date, rate.base_currency, rate.quote_currency, rate.rate
+                        )
+                    })?;
+                stmt.execute([
+                    rate.rate_date.as_str(),
+                    rate.base_currency.as_str(),
+                    rate.quote_currency.as_str(),
+                    rate.rate.trim(),
+                    inverse.as_str(),
+                ])?;
+            }
+        }
+
+        let target = quote_literal(target_currency);
+        self.conn.execute(
+            &format!(
+                "CREATE OR REPLACE TEMP TABLE fx_lookup AS \
+                 SELECT rate_date, base_currency AS currency, CAST(rate AS DECIMAL(38,20)) AS rate \
+                 FROM fx_rates WHERE quote_currency = {target} \
+                 UNION ALL \
+                 SELECT rate_date, quote_currency AS currency, inverse_rate AS rate \
+                 FROM fx_rates WHERE base_currency = {target} \
+                   AND NOT EXISTS (SELECT 1 FROM fx_rates d WHERE d.quote_currency = {target} \
+                     AND d.base_currency = fx_rates.quote_currency AND d.rate_date = fx_rates.rate_date)",
+                target = target
+            ),
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// 将金额列转换为指定小数位数的 DECIMAL（四舍五入）
+    fn round_amount_column(&self, table_name: &str, amount_field: &str, scale: u32) -> Result<()> {
+        let sql = format!(
+            "ALTER TABLE {} ALTER COLUMN \"{}\" SET DATA TYPE DECIMAL(18,{}) \
+             USING ROUND(CAST(\"{}\" AS DECIMAL(18,{})), {})",
+            table_name, amount_field, scale, amount_field, MAX_AMOUNT_SCALE, scale
+        );
+        self.conn
+            .execute(&sql, [])
+            .with_context(|| format!("金额字段 '{}' 精度转换失败", amount_field))?;
+        Ok(())
+    }
+
+    /// 字段类型对应的列类型：金额和手续费使用定点小数，时间使用 TIMESTAMP，其余为 VARCHAR
+    /// 时间列不带时区：清洗时已统一换算到对账统一时区 reconciliationTimezone，两侧在同一时区下直接比较
+    pub fn column_type(field_type: &str) -> &'static str {
+        match field_type {
+            "OrderAmount" | "OrderFee" => "DECIMAL(18,4)",
+            "OrderTime" => "TIMESTAMP",
+            _ => "VARCHAR",
+        }
+    }
+
+    /// 清洗后的列先以 VARCHAR 计算，再按字段类型转换为实际列类型
+    /// 金额（手续费）为空视为 NULL，无法转换时报错；时间无法解析的值置为 NULL（换算时已记录警告）
+    fn convert_column_type(&self, table_name: &str, field_name: &str, field_type: &str) -> Result<()> {
+        let column_type = Self::column_type(field_type);
+        let using_expr = match field_type {
+            "OrderAmount" | "OrderFee" => format!(
+                "CAST(NULLIF(TRIM(CAST(\"{}\" AS VARCHAR)), '') AS {})",
+                field_name, column_type
+            ),
+            "OrderTime" => format!("TRY_CAST(\"{}\" AS {})", field_name, column_type),
+            // 币种统一为大写代码
+            "Currency" => format!("NULLIF(UPPER(TRIM(CAST(\"{}\" AS VARCHAR))), '')", field_name),
+            _ => return Ok(()),
+        };
+
+        let sql = format!(
+            "ALTER TABLE {} ALTER COLUMN \"{}\" SET DATA TYPE {} USING {}",
+            table_name, field_name, column_type, using_expr
+        );
+        self.conn.execute(&sql, [])?;
+        Ok(())
+    }
+
+    /// 源列取值；小数点为逗号时去掉千分位的点，再把逗号换成点（1.234,56 → 1234.56）
+    fn source_column_expr(column: &str, decimal_comma: bool) -> String {
+        if decimal_comma {
+            expression::decimal_comma_to_point(&format!("\"{}\"", column))
+        } else {
+            format!("\"{}\"", column)
+        }
+    }
+
+    /// 小数点为逗号时，表中仍为文本的列（原始列）；已清洗的金额、手续费字段已是定点小数，不再转换
+    fn decimal_comma_columns(&self, table_name: &str, decimal_separator: &str) -> Result<Vec<String>> {
+        if decimal_separator.trim() != "," {
+            return Ok(Vec::new());
+        }
+        Ok(self
+            .get_table_column_types(table_name)?
+            .into_iter()
+            .filter(|(_, column_type)| column_type.eq_ignore_ascii_case("VARCHAR"))
+            .map(|(column, _)| column)
+            .collect())
+    }
+
+    /// 借贷分列：金额 = |贷方| - |借方|（debit_negative 为 false 时取反），两列都为空时为 NULL
+    /// 两列先取绝对值，借方列本身已带负号的文件也不会被负负得正
+    fn debit_credit_expr(sign: &AmountSignConfig, decimal_comma: bool) -> String {
+        let amount = |column: &str| {
+            format!(
+                "ABS(CAST(NULLIF(TRIM(CAST({} AS VARCHAR)), '') AS DECIMAL(18,4)))",
+                Self::source_column_expr(column, decimal_comma)
+            )
+        };
+        let (positive, negative) = if sign.debit_negative {
+            (amount(&sign.credit_column), amount(&sign.debit_column))
+        } else {
+            (amount(&sign.debit_column), amount(&sign.credit_column))
+        };
+        format!(
+            "CASE WHEN {p} IS NULL AND {n} IS NULL THEN NULL ELSE COALESCE({p}, 0) - COALESCE({n}, 0) END",
+            p = positive,
+            n = negative
+        )
+    }
+
+    /// 借贷标识：标识为借方的取负数（debit_negative 为 false 时取正数），其余取相反符号
+    fn indicator_sign_expr(column_expr: &str, sign: &AmountSignConfig) -> String {
+        let debit_values: Vec<String> = sign
+            .effective_debit_values()
+            .iter()
+            .map(|v| quote_literal(&v.trim().to_uppercase()))
+            .collect();
+        let amount = format!("ABS(CAST({} AS DECIMAL(18,4)))", column_expr);
+        let (debit_amount, credit_amount) = if sign.debit_negative {
+            (format!("-{}", amount), amount)
+        } else {
+            (amount.clone(), format!("-{}", amount))
+        };
+        format!(
+            "CASE WHEN UPPER(TRIM(CAST(\"{}\" AS VARCHAR))) IN ({}) THEN {} ELSE {} END",
+            sign.indicator_column,
+            debit_values.join(", "),
+            debit_amount,
+            credit_amount
+        )
+    }
+
+    /// 字段配置的日期解析格式：取 DATE_PARSE 规则的值，规则值为空时使用字段的 rule_config；
+    /// OrderTime 字段未配置 DATE_PARSE 规则时，rule_config 也可直接作为解析格式
+    fn date_patterns(mapping: &ColumnMapping) -> Vec<String> {
+        let date_rules: Vec<&FormatRule> = mapping
+            .format_rules
+            .iter()
+            .filter(|r| r.operation == "DATE_PARSE")
+            .collect();
+
+        let mut patterns: Vec<String> = date_rules
+            .iter()
+            .filter(|r| !r.value.trim().is_empty())
+            .map(|r| r.value.clone())
+            .collect();
+        let uses_rule_config = !date_rules.is_empty() || mapping.field_type == "OrderTime";
+        if patterns.is_empty() && uses_rule_config && !mapping.rule_config.trim().is_empty() {
+            patterns.push(mapping.rule_config.clone());
+        }
+        patterns
+    }
+
+    /// 将时间列解析后换算到目标时区，统一为 YYYY-MM-DD HH:MM:SS 格式
+    /// 无法解析的值记录警告，随后转换为 TIMESTAMP 列时置为 NULL
+    fn convert_time_column(
+        &self,
+        table_name: &str,
+        field_name: &str,
+        rule_type: &str,
+        patterns: &[String],
+        source_timezone: &str,
+        target_timezone: &str,
+    ) -> Result<()> {
+        let source_tz = time_parser::parse_timezone(source_timezone)?;
+        let target_tz = time_parser::parse_timezone(target_timezone)?;
+
+        let mut unparsed = 0;
+        let replacements: Vec<(String, String)> = self
+            .distinct_values(table_name, field_name)?
+            .into_iter()
+            .filter_map(|raw| {
+                match time_parser::convert_time(&raw, rule_type, patterns, source_tz, target_tz) {
+                    Some(t) => Some((raw, t.format(time_parser::TIME_FORMAT).to_string())),
+                    None => {
+                        unparsed += 1;
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        if unparsed > 0 {
+            log::warn!(
+                "{} 中字段 {} 有 {} 个时间值无法解析，将置为空",
+                table_name, field_name, unparsed
+            );
+        }
+
+        self.replace_column_values(table_name, field_name, &replacements)
+    }
+
+    /// 获取某列所有非空的去重值（按文本读取）
+    fn distinct_values(&self, table_name: &str, column: &str) -> Result<Vec<String>> {
+        let sql = format!(
+            "SELECT DISTINCT CAST(\"{}\" AS VARCHAR) FROM {} WHERE \"{}\" IS NOT NULL",
+            column, table_name, column
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let values = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .filter_map(|r| r.ok())
+            .collect();
+        Ok(values)
+    }
+
+    /// 按 (原值, 新值) 列表批量替换某列的值，未列出的值保持不变
+    fn replace_column_values(
+        &self,
+        table_name: &str,
+        column: &str,
+        replacements: &[(String, String)],
+    ) -> Result<()> {
+        if replacements.is_empty() {
+            return Ok(());
+        }
+
+        self.conn.execute("DROP TABLE IF EXISTS value_replacements", [])?;
+        self.conn.execute(
+            "CREATE TEMP TABLE value_replacements (raw VARCHAR, replacement VARCHAR)",
+            [],
+        )?;
+        {
+            let mut stmt = self
+                .conn
+                .prepare("INSERT INTO value_replacements VALUES (?, ?)")?;
+            for (raw, replacement) in replacements {
+                stmt.execute([raw.as_str(), replacement.as_str()])?;
+            }
+        }
+
+        let update_sql = format!(
+            "UPDATE {table} SET \"{col}\" = r.replacement FROM value_replacements r \
+             WHERE CAST({table}.\"{col}\" AS VARCHAR) = r.raw",
+            table = table_name,
+            col = column
+        );
+        self.conn.execute(&update_sql, [])?;
+        self.conn.execute("DROP TABLE value_replacements", [])?;
+        Ok(())
+    }
+
+    fn apply_format_rule(
+        &self,
+        column_expr: &str,
+        rule: &FormatRule,
+        decimal_comma_columns: &[String],
+    ) -> Result<String> {
+        let expr = self.format_rule_expr(column_expr, rule)?;
+
+        // 条件规则：满足条件的行使用规则结果，其余行保持原值
+        match &rule.condition {
+            Some(condition) if rule.operation != "DATE_PARSE" => Ok(format!(
+                "CASE WHEN {} THEN CAST({} AS VARCHAR) ELSE CAST({} AS VARCHAR) END",
+                Self::rule_condition_sql(condition, decimal_comma_columns)?,
+                expr,
+                column_expr
+            )),
+            _ => Ok(expr),
+        }
+    }
+
+    /// 规则条件对应的 SQL 判断；条件列为空时不满足条件
+    /// 数值比较时，decimal_comma_columns 中的列按逗号小数点转换后再比较
+    fn rule_condition_sql(condition: &RuleCondition, decimal_comma_columns: &[String]) -> Result<String> {
+        let column = format!("CAST(\"{}\" AS VARCHAR)", condition.column);
+        let numeric = |op: &str| -> Result<String> {
+            let value: f64 = condition
+                .value
+                .trim()
+                .parse()
+                .with_context(|| format!("条件值不是有效数字: {}", condition.value))?;
+            let number_text = if decimal_comma_columns.contains(&condition.column) {
+                expression::decimal_comma_to_point(&column)
+            } else {
+                format!("TRIM({})", column)
+            };
+            Ok(format!(
+                "TRY_CAST(NULLIF({}, '') AS DECIMAL(18,4)) {} {}",
+                number_text, op, value
+            ))
+        };
+
+        let sql = match condition.operator.as_str() {
+            "EQUALS" => format!("{} = {}", column, quote_literal(&condition.value)),
+            "NOT_EQUALS" => format!("{} <> {}", column, quote_literal(&condition.value)),
+            "IN" => {
+                let values: Vec<String> = condition
+                    .value
+                    .split(',')
+                    .map(|v| quote_literal(v.trim()))
+                    .collect();
+                format!("{} IN ({})", column, values.join(", "))
+            }
+            "REGEX" => format!(
+                "REGEXP_MATCHES({}, {})",
+                column,
+                quote_literal(&condition.value)
+            ),
+            "GT" => numeric(">")?,
+            "GTE" => numeric(">=")?,
+            "LT" => numeric("<")?,
+            "LTE" => numeric("<=")?,
+            other => anyhow::bail!("不支持的条件运算符: {}", other),
+        };
+        Ok(sql)
+    }
+
+    fn format_rule_expr(&self, column_expr: &str, rule: &FormatRule) -> Result<String> {
         let expr = match rule.operation.as_str() {
             "DEL_PRE" => {
                 // Delete first N characters
@@ -204,14 +1024,54 @@ impl DataProcessor {
                 // Extract value between brackets
                 format!("REGEXP_EXTRACT({}, '\\[(.+?)\\]', 1)", column_expr)
             }
+            "REGEX_EXTRACT" => {
+                // 按正则提取捕获组，未匹配时为 NULL
+                format!(
+                    "NULLIF(REGEXP_EXTRACT(CAST({} AS VARCHAR), {}, {}), '')",
+                    column_expr,
+                    quote_literal(&rule.value),
+                    rule.group.unwrap_or(1)
+                )
+            }
+            "REGEX_REPLACE" => {
+                // 替换所有匹配
+                format!(
+                    "REGEXP_REPLACE(CAST({} AS VARCHAR), {}, {}, 'g')",
+                    column_expr,
+                    quote_literal(&rule.value),
+                    quote_literal(&rule.replacement)
+                )
+            }
+            "LOOKUP" => {
+                // 通过对照表映射，未找到时按 missing_policy 处理
+                let key = format!("TRIM(CAST({} AS VARCHAR))", column_expr);
+                let lookup = format!(
+                    "(SELECT l.lookup_value FROM lookup_entries l WHERE l.table_name = {} AND l.lookup_key = {})",
+                    quote_literal(rule.value.trim()),
+                    key
+                );
+                match rule.missing_policy.as_str() {
+                    "NULL" => lookup,
+                    "FLAG" => format!("COALESCE({}, 'UNMAPPED:' || {})", lookup, key),
+                    _ => format!("COALESCE({}, CAST({} AS VARCHAR))", lookup, column_expr),
+                }
+            }
             "DIVIDE_NUMBER" => {
-                // Divide by a number
-                let n: f64 = rule.value.parse().unwrap_or(1.0);
-                format!("CAST({} AS DOUBLE) / {}", column_expr, n)
+                // 以定点小数相除：除数的倒数是有限小数时（10、100、0.5 等）改为乘以倒数，结果精确
+                let divisor = parse_divisor(&rule.value)?;
+                let amount = format!("CAST({} AS DECIMAL(18,4))", column_expr);
+                match exact_reciprocal(divisor) {
+                    Some(reciprocal) => format!("CAST({} * {} AS DECIMAL(18,4))", amount, reciprocal),
+                    None => format!("CAST({} / {} AS DECIMAL(18,4))", amount, divisor),
+                }
+            }
+            "NEGATE_NUMBER" => {
+                // 取相反数（如借记金额转为负数）
+                format!("-CAST({} AS DECIMAL(18,4))", column_expr)
             }
             "ABS_VALUE" => {
                 // Absolute value
-                format!("ABS(CAST({} AS DOUBLE))", column_expr)
+                format!("ABS(CAST({} AS DECIMAL(18,4)))", column_expr)
             }
             "ADD_CHAR_PRE" => {
                 // Add characters before
@@ -225,80 +1085,273 @@ impl DataProcessor {
                 // Convert Xendit timestamp format
                 format!("STRPTIME({}, '%Y-%m-%dT%H:%M:%S')", column_expr)
             }
+            "DATE_PARSE" => {
+                // 按配置的格式解析日期，在 SQL 规则执行后由 convert_time_column 处理
+                column_expr.to_string()
+            }
             _ => column_expr.to_string(),
         };
         
         Ok(expr)
     }
 
-    pub fn remove_duplicates(&self, table_name: &str, id_field: &str) -> Result<()> {
+    /// 按行过滤条件删除不参与对账的行，返回被排除的行（excluded_by 列为第一个命中的过滤条件）
+    /// 条件判断结果为空时：INCLUDE 视为不满足，EXCLUDE 视为不满足
+    pub fn apply_row_filters(
+        &self,
+        table_name: &str,
+        filters: &[RowFilter],
+        decimal_separator: &str,
+    ) -> Result<Vec<HashMap<String, serde_json::Value>>> {
+        if filters.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let decimal_comma_columns = self.decimal_comma_columns(table_name, decimal_separator)?;
+        let mut reasons = Vec::new();
+        for filter in filters {
+            let condition = Self::rule_condition_sql(&filter.condition, &decimal_comma_columns)?;
+            let reason = if filter.name.trim().is_empty() {
+                format!(
+                    "{} {} {} {}",
+                    filter.action, filter.condition.column, filter.condition.operator, filter.condition.value
+                )
+            } else {
+                filter.name.clone()
+            };
+            let excluded_when = match filter.action.as_str() {
+                "INCLUDE" => format!("NOT COALESCE({}, FALSE)", condition),
+                "EXCLUDE" => format!("COALESCE({}, FALSE)", condition),
+                other => anyhow::bail!("不支持的行过滤方式: {}（应为 INCLUDE 或 EXCLUDE）", other),
+            };
+            reasons.push(format!("WHEN {} THEN {}", excluded_when, quote_literal(&reason)));
+        }
+
+        self.conn.execute(
+            &format!(
+                "CREATE OR REPLACE TEMP TABLE row_filter_result AS \
+                 SELECT rowid AS row_id, CASE {} END AS excluded_by FROM {}",
+                reasons.join(" "),
+                table_name
+            ),
+            [],
+        )?;
+
+        let excluded = self.execute_query_to_json(&format!(
+            "SELECT t.*, f.excluded_by FROM {} t \
+             INNER JOIN row_filter_result f ON t.rowid = f.row_id \
+             WHERE f.excluded_by IS NOT NULL",
+            table_name
+        ))?;
+        self.conn.execute(
+            &format!(
+                "DELETE FROM {} WHERE rowid IN \
+                 (SELECT row_id FROM row_filter_result WHERE excluded_by IS NOT NULL)",
+                table_name
+            ),
+            [],
+        )?;
+
+        Ok(excluded)
+    }
+
+    pub fn remove_duplicates(&self, table_name: &str, id_fields: &[String]) -> Result<()> {
+        let group_by = id_fields
+            .iter()
+            .map(|f| format!("\"{}\"", f))
+            .collect::<Vec<_>>()
+            .join(", ");
         let sql = format!(
-            "DELETE FROM {} WHERE rowid NOT IN (SELECT MIN(rowid) FROM {} GROUP BY \"{}\")",
-            table_name, table_name, id_field
+            "DELETE FROM {} WHERE rowid NOT IN (SELECT MIN(rowid) FROM {} GROUP BY {})",
+            table_name, table_name, group_by
         );
         self.conn.execute(&sql, [])?;
         Ok(())
     }
 
+    /// 状态标准化：按映射将清洗后的状态写入 normalized_status，比较时忽略大小写和首尾空格
+    /// 返回未被任何映射覆盖的原始状态及行数；配置了默认状态时，这些行标准化为默认状态
     pub fn normalize_status(
         &self,
         table_name: &str,
         status_field: &str,
         mappings: &[StatusMapping],
-    ) -> Result<()> {
+        default_status: &str,
+    ) -> Result<Vec<(String, usize)>> {
         // Add normalized status column
         let alter_sql = format!(
             "ALTER TABLE {} ADD COLUMN normalized_status VARCHAR",
             table_name
         );
         let _ = self.conn.execute(&alter_sql, []);
+
+        let status_expr = format!("UPPER(TRIM(CAST(\"{}\" AS VARCHAR)))", status_field);
         
-        // Apply status mappings
-        for mapping in mappings {
-            if !mapping.source_status.is_empty() {
-                let status_list = mapping.source_status
+        // Apply status mappings：按 EXACT > RANGE > WILDCARD > REGEX 的优先级依次匹配，
+        // 同一类型内按配置顺序，已标准化的行不会被后面的映射覆盖
+        for match_type in STATUS_MATCH_PRECEDENCE {
+            for mapping in mappings.iter().filter(|m| m.effective_match_type() == match_type) {
+                if mapping.source_status.is_empty() {
+                    continue;
+                }
+                let conditions = mapping
+                    .source_status
                     .iter()
-                    .map(|s| format!("'{}'", s))
-                    .collect::<Vec<_>>()
-                    .join(", ");
+                    .map(|pattern| Self::status_pattern_condition(&status_expr, match_type, pattern))
+                    .collect::<Result<Vec<_>>>()?
+                    .join(" OR ");
                 
                 let update_sql = format!(
-                    "UPDATE {} SET normalized_status = '{}' WHERE \"{}\" IN ({})",
-                    table_name, mapping.target_status, status_field, status_list
+                    "UPDATE {} SET normalized_status = {} WHERE normalized_status IS NULL AND ({})",
+                    table_name, quote_literal(&mapping.target_status), conditions
                 );
                 self.conn.execute(&update_sql, [])?;
             }
         }
+
+        // 统计未映射的原始状态
+        let unmapped: Vec<(String, usize)> = {
+            let sql = format!(
+                "SELECT COALESCE(CAST(\"{}\" AS VARCHAR), ''), COUNT(*) FROM {} \
+                 WHERE normalized_status IS NULL GROUP BY 1 ORDER BY 2 DESC, 1",
+                status_field, table_name
+            );
+            let mut stmt = self.conn.prepare(&sql)?;
+            let rows = stmt.query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as usize))
+            })?;
+            rows.filter_map(|r| r.ok()).collect()
+        };
+
+        if !default_status.trim().is_empty() && !unmapped.is_empty() {
+            let update_sql = format!(
+                "UPDATE {} SET normalized_status = {} WHERE normalized_status IS NULL",
+                table_name,
+                quote_literal(default_status.trim())
+            );
+            self.conn.execute(&update_sql, [])?;
+        }
         
-        Ok(())
+        Ok(unmapped)
+    }
+
+    /// 单个状态模式的匹配条件（status_expr 已转为大写并去除首尾空格）
+    fn status_pattern_condition(status_expr: &str, match_type: &str, pattern: &str) -> Result<String> {
+        let condition = match match_type {
+            "EXACT" => format!("{} = {}", status_expr, quote_literal(&pattern.trim().to_uppercase())),
+            "WILDCARD" => {
+                // * 匹配任意字符，? 匹配单个字符，其余字符按字面匹配
+                let like = pattern
+                    .trim()
+                    .to_uppercase()
+                    .replace('\\', "\\\\")
+                    .replace('%', "\\%")
+                    .replace('_', "\\_")
+                    .replace('*', "%")
+                    .replace('?', "_");
+                format!("{} LIKE {} ESCAPE '\\'", status_expr, quote_literal(&like))
+            }
+            "REGEX" => format!("REGEXP_MATCHES({}, {}, 'i')", status_expr, quote_literal(pattern)),
+            "RANGE" => {
+                let (min, max) = parse_status_range(pattern)?;
+                format!(
+                    "TRY_CAST({} AS DECIMAL(18,4)) BETWEEN {} AND {}",
+                    status_expr, min, max
+                )
+            }
+            other => anyhow::bail!("不支持的状态匹配方式: {}", other),
+        };
+        Ok(condition)
     }
 
     pub fn perform_reconciliation(
         &self,
-        order_id_field: &str,
-        bank_id_field: &str,
-        order_amount_field: &str,
-        bank_amount_field: &str,
+        match_config: &MatchConfig,
+        source_a: &SourceFields,
+        source_b: &SourceFields,
     ) -> Result<ReconciliationResult> {
+        let has_amount = !source_a.amount_field.is_empty() && !source_b.amount_field.is_empty();
+
+        // 金额按对账币种的小数位数取整，之后的比较和输出都基于精确小数
+        if has_amount {
+            let scale = match_config.amount_scale();
+            self.round_amount_column("source_a", &source_a.amount_field, scale)?;
+            self.round_amount_column("source_b", &source_b.amount_field, scale)?;
+        }
+
+        let amount_a = format!("a.\"{}\"", source_a.amount_field);
+        let amount_b = format!("b.\"{}\"", source_b.amount_field);
+        self.reset_match_tables()?;
+
+        // 拆分付款 / 批量结算：一侧只有一条、另一侧多条且合计一致的分组先于一对一配对识别，
+        // 否则同一 ID 的多笔转账会被一对一配对拆成一条金额差异和若干单边账
+        let group_match = match_config
+            .group_match
+            .as_ref()
+            .filter(|group_match| group_match.enabled && has_amount);
+        let mut grouped_matched = match group_match {
+            Some(group_match) => {
+                self.build_group_matches(match_config, group_match, &amount_a, &amount_b, true)?
+            }
+            None => Vec::new(),
+        };
+
+        // 按轮次建立 A/B 一对一配对关系
+        self.build_match_pairs(match_config, source_a, source_b)?;
+
+        let tolerance = Self::amount_tolerance_condition(match_config, &amount_a, &amount_b);
+
+        // 启用手续费时附加 feeVariance：实际扣费（总额 - 净额）与预期手续费之差
+        let fee_variance = match &match_config.fee {
+            Some(fee) if fee.enabled && has_amount => match fee.net_side.as_str() {
+                "A" => format!(", {} - {} AS \"feeVariance\"", amount_b, amount_a),
+                _ => format!(", {} - {} AS \"feeVariance\"", amount_a, amount_b),
+            },
+            _ => String::new(),
+        };
+        let paired_sql = format!(
+            "SELECT a.*, b.*, p.match_pass{} FROM match_pairs p \
+             INNER JOIN source_a a ON a.rowid = p.a_row \
+             INNER JOIN source_b b ON b.rowid = p.b_row",
+            fee_variance
+        );
+        let paired_sql = paired_sql.as_str();
+
         // Matched records (status and amount both match)
-        let matched_sql = if !order_amount_field.is_empty() && !bank_amount_field.is_empty() {
+        let matched_sql = if has_amount {
             format!(
-                "SELECT a.*, b.* FROM source_a a INNER JOIN source_b b ON a.\"{}\" = b.\"{}\" WHERE a.normalized_status = b.normalized_status AND CAST(a.\"{}\" AS DOUBLE) = CAST(b.\"{}\" AS DOUBLE)",
-                order_id_field, bank_id_field, order_amount_field, bank_amount_field
+                "{} WHERE a.normalized_status = b.normalized_status AND {} = {}",
+                paired_sql, amount_a, amount_b
             )
         } else {
             format!(
-                "SELECT a.*, b.* FROM source_a a INNER JOIN source_b b ON a.\"{}\" = b.\"{}\" WHERE a.normalized_status = b.normalized_status",
-                order_id_field, bank_id_field
+                "{} WHERE a.normalized_status = b.normalized_status",
+                paired_sql
             )
         };
         let matched = self.execute_query_to_json(&matched_sql)?;
 
-        // Records with amount difference
-        let diff_amount_sql = if !order_amount_field.is_empty() && !bank_amount_field.is_empty() {
+        // Records matched within amount tolerance
+        let matched_with_tolerance = match (&tolerance, has_amount) {
+            (Some(tolerance), true) => {
+                let sql = format!(
+                    "{} WHERE a.normalized_status = b.normalized_status AND {} != {} AND {}",
+                    paired_sql, amount_a, amount_b, tolerance
+                );
+                self.execute_query_to_json(&sql)?
+            }
+            _ => Vec::new(),
+        };
+
+        // Records with amount difference; 任一侧金额为空的配对也归入这里，不会从结果中消失
+        let diff_amount_sql = if has_amount {
+            let beyond_tolerance = tolerance
+                .as_ref()
+                .map(|t| format!(" AND ({}) IS NOT TRUE", t))
+                .unwrap_or_default();
             format!(
-                "SELECT a.*, b.* FROM source_a a INNER JOIN source_b b ON a.\"{}\" = b.\"{}\" WHERE a.normalized_status = b.normalized_status AND CAST(a.\"{}\" AS DOUBLE) != CAST(b.\"{}\" AS DOUBLE)",
-                order_id_field, bank_id_field, order_amount_field, bank_amount_field
+                "{} WHERE a.normalized_status = b.normalized_status AND ({} = {}) IS NOT TRUE{}",
+                paired_sql, amount_a, amount_b, beyond_tolerance
             )
         } else {
             // 如果没有金额字段，返回空结果
@@ -306,18 +1359,38 @@ impl DataProcessor {
         };
         let diff_amount = self.execute_query_to_json(&diff_amount_sql)?;
 
-        // Only in source_a
-        let only_in_a_sql = format!(
-            "SELECT a.* FROM source_a a LEFT JOIN source_b b ON a.\"{}\" = b.\"{}\" WHERE b.\"{}\" IS NULL",
-            order_id_field, bank_id_field, bank_id_field
+        // Records paired by key but with different (or missing) normalized status
+        let status_mismatch_sql = format!(
+            "{} WHERE (a.normalized_status = b.normalized_status) IS NOT TRUE",
+            paired_sql
         );
+        let status_mismatch = self.execute_query_to_json(&status_mismatch_sql)?;
+
+        // Grouped matches (many-to-many) among the rows left after 1:1 pairing
+        if let Some(group_match) = group_match {
+            grouped_matched.extend(self.build_group_matches(
+                match_config,
+                group_match,
+                &amount_a,
+                &amount_b,
+                false,
+            )?);
+        }
+
+        // Fuzzy ID suggestions among the remaining one-sided rows (not auto-matched)
+        let suggested_matches = match &match_config.fuzzy_match {
+            Some(fuzzy_match) if fuzzy_match.enabled => {
+                self.find_suggested_matches(match_config, fuzzy_match)?
+            }
+            _ => Vec::new(),
+        };
+
+        // Only in source_a
+        let only_in_a_sql = format!("SELECT a.* FROM source_a a WHERE {}", Self::unmatched_filter("a"));
         let only_in_a = self.execute_query_to_json(&only_in_a_sql)?;
 
         // Only in source_b
-        let only_in_b_sql = format!(
-            "SELECT b.* FROM source_b b LEFT JOIN source_a a ON b.\"{}\" = a.\"{}\" WHERE a.\"{}\" IS NULL",
-            bank_id_field, order_id_field, order_id_field
-        );
+        let only_in_b_sql = format!("SELECT b.* FROM source_b b WHERE {}", Self::unmatched_filter("b"));
         let only_in_b = self.execute_query_to_json(&only_in_b_sql)?;
 
         Ok(ReconciliationResult {
@@ -325,9 +1398,450 @@ impl DataProcessor {
             only_in_a,
             only_in_b,
             diff_amount,
+            matched_with_tolerance,
+            status_mismatch,
+            grouped_matched,
+            suggested_matches,
+            excluded_a: Vec::new(),
+            excluded_b: Vec::new(),
         })
     }
 
+    /// 逐轮执行匹配，把配对结果写入临时表 match_pairs(a_row, b_row, match_pass)
+    /// 每轮只考虑前面轮次尚未配对、也未被分组匹配占用的记录，且每条记录最多配对一次
+    fn build_match_pairs(
+        &self,
+        match_config: &MatchConfig,
+        source_a: &SourceFields,
+        source_b: &SourceFields,
+    ) -> Result<()> {
+        let passes = match_config.effective_match_passes();
+        let uses_time_window = passes
+            .iter()
+            .any(|pass| Self::pass_time_window(match_config, pass).is_some());
+        if uses_time_window {
+            self.build_match_times("source_a", "match_times_a", source_a)
+                .context("数据源A时间换算失败")?;
+            self.build_match_times("source_b", "match_times_b", source_b)
+                .context("数据源B时间换算失败")?;
+        }
+
+        // 要求符号一致时，退款（负数）不会与付款（正数）配对；金额为空的记录不受限制
+        let sign_filter = if match_config.require_sign_agreement
+            && !source_a.amount_field.is_empty()
+            && !source_b.amount_field.is_empty()
+        {
+            format!(
+                " AND (a.\"{a}\" IS NULL OR b.\"{b}\" IS NULL OR SIGN(a.\"{a}\") = SIGN(b.\"{b}\"))",
+                a = source_a.amount_field,
+                b = source_b.amount_field
+            )
+        } else {
+            String::new()
+        };
+
+        for pass in passes {
+            let join_on = Self::key_join_condition(
+                &pass.source_a_key_fields,
+                &pass.source_b_key_fields,
+            )
+            .with_context(|| format!("匹配轮次 '{}' 配置错误", pass.name))?;
+
+            // 先在每个匹配键分组内按时间（或行号）排序，第 k 条 A 与第 k 条 B 一次性配对
+            let time_window = Self::pass_time_window(match_config, &pass);
+            let sign_amounts = if sign_filter.is_empty() {
+                None
+            } else {
+                Some((source_a.amount_field.as_str(), source_b.amount_field.as_str()))
+            };
+            self.conn.execute(
+                &Self::ranked_pairs_sql(&pass, time_window, sign_amounts),
+                [],
+            )?;
+
+            // 时间窗口：两侧 UTC 时间差不超过 N 小时，候选中优先选择时间最接近的记录
+            let (time_join, time_filter, order_b, order_a) =
+                match Self::pass_time_window(match_config, &pass) {
+                    Some(hours) => (
+                        " LEFT JOIN match_times_a ta ON ta.row_id = a.rowid \
+                          LEFT JOIN match_times_b tb ON tb.row_id = b.rowid"
+                            .to_string(),
+                        format!(
+                            " AND ABS(DATE_DIFF('second', ta.ts, tb.ts)) <= {}",
+                            (hours * 3600.0).round() as i64
+                        ),
+                        "ABS(DATE_DIFF('second', ta.ts, tb.ts)), b.rowid",
+                        "ABS(DATE_DIFF('second', ta.ts, tb.ts)), a.rowid",
+                    ),
+                    None => (String::new(), String::new(), "b.rowid", "a.rowid"),
+                };
+
+            let insert_sql = format!(
+                "INSERT INTO match_pairs \
+                 SELECT a_row, b_row, {} FROM ( \
+                     SELECT a.rowid AS a_row, b.rowid AS b_row, \
+                            ROW_NUMBER() OVER (PARTITION BY a.rowid ORDER BY {}) AS rn_a, \
+                            ROW_NUMBER() OVER (PARTITION BY b.rowid ORDER BY {}) AS rn_b \
+                     FROM source_a a INNER JOIN source_b b ON {}{} \
+                     WHERE {} AND {}{}{} \
+                 ) WHERE rn_a = 1 AND rn_b = 1",
+                quote_literal(&pass.name),
+                order_b,
+                order_a,
+                join_on,
+                time_join,
+                Self::unmatched_filter("a"),
+                Self::unmatched_filter("b"),
+                time_filter,
+                sign_filter
+            );
+
+            // 按名次配对后剩下的候选（时间窗口外、金额为空等）逐轮取互为最优的配对，直到没有新的配对产生
+            loop {
+                let inserted = self.conn.execute(&insert_sql, [])?;
+                if inserted == 0 {
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 按名次配对：两侧各自在匹配键（要求符号一致时再加金额符号）分组内按时间、行号排序，
+    /// 同一分组内名次相同的记录配对；有时间窗口时超出窗口的配对不插入
+    fn ranked_pairs_sql(
+        pass: &MatchPass,
+        time_window: Option<f64>,
+        sign_amounts: Option<(&str, &str)>,
+    ) -> String {
+        let ranked = |alias: &str, table: &str, keys: &[String], amount: Option<&str>| {
+            let mut partition: Vec<String> =
+                keys.iter().map(|k| format!("{}.\"{}\"", alias, k)).collect();
+            if let Some(amount) = amount {
+                partition.push(format!("SIGN({}.\"{}\")", alias, amount));
+            }
+            let columns: Vec<String> = partition
+                .iter()
+                .enumerate()
+                .map(|(i, expr)| format!("{} AS key{}", expr, i))
+                .collect();
+            let (time_join, ts, order) = match time_window {
+                Some(_) => (
+                    format!(" LEFT JOIN match_times_{a} t{a} ON t{a}.row_id = {a}.rowid", a = alias),
+                    format!("t{}.ts", alias),
+                    format!("t{a}.ts, {a}.rowid", a = alias),
+                ),
+                None => (String::new(), "NULL".to_string(), format!("{}.rowid", alias)),
+            };
+            format!(
+                "SELECT {alias}.rowid AS row_id, {columns}, {ts} AS ts, \
+                        ROW_NUMBER() OVER (PARTITION BY {partition} ORDER BY {order}) AS rn \
+                 FROM {table} {alias}{time_join} \
+                 WHERE {filter}",
+                alias = alias,
+                columns = columns.join(", "),
+                ts = ts,
+                partition = partition.join(", "),
+                order = order,
+                table = table,
+                time_join = time_join,
+                filter = Self::unmatched_filter(alias)
+            )
+        };
+
+        let key_count = pass.source_a_key_fields.len() + usize::from(sign_amounts.is_some());
+        let join_on: Vec<String> = (0..key_count)
+            .map(|i| format!("ra.key{i} = rb.key{i}", i = i))
+            .chain(std::iter::once("ra.rn = rb.rn".to_string()))
+            .collect();
+        let window_filter = time_window
+            .map(|hours| {
+                format!(
+                    " WHERE ABS(DATE_DIFF('second', ra.ts, rb.ts)) <= {}",
+                    (hours * 3600.0).round() as i64
+                )
+            })
+            .unwrap_or_default();
+
+        format!(
+            "INSERT INTO match_pairs \
+             SELECT ra.row_id, rb.row_id, {} \
+             FROM ({}) ra INNER JOIN ({}) rb ON {}{}",
+            quote_literal(&pass.name),
+            ranked("a", "source_a", &pass.source_a_key_fields, sign_amounts.map(|(a, _)| a)),
+            ranked("b", "source_b", &pass.source_b_key_fields, sign_amounts.map(|(_, b)| b)),
+            join_on.join(" AND "),
+            window_filter
+        )
+    }
+
+    /// 某一轮匹配实际生效的时间窗口（小时），轮次配置优先于全局配置
+    fn pass_time_window(match_config: &MatchConfig, pass: &MatchPass) -> Option<f64> {
+        let hours = pass
+            .max_time_diff_hours
+            .unwrap_or(match_config.max_time_diff_hours);
+        if hours > 0.0 {
+            Some(hours)
+        } else {
+            None
+        }
+    }
+
+    /// 将时间字段按其所在时区换算为 UTC，写入临时表 target_table(row_id, ts)
+    /// 无法解析的时间为 NULL，此类记录不会满足时间窗口条件
+    fn build_match_times(&self, table_name: &str, target_table: &str, fields: &SourceFields) -> Result<()> {
+        if fields.time_field.is_empty() {
+            anyhow::bail!("启用了时间窗口，但未配置 OrderTime 字段");
+        }
+        let tz = time_parser::parse_timezone(&fields.timezone)?;
+
+        let raw_values = self.distinct_values(table_name, &fields.time_field)?;
+
+        let map_table = format!("{}_map", target_table);
+        self.conn.execute(&format!("DROP TABLE IF EXISTS {}", map_table), [])?;
+        self.conn.execute(
+            &format!("CREATE TEMP TABLE {} (raw VARCHAR, ts VARCHAR)", map_table),
+            [],
+        )?;
+        {
+            let mut stmt = self
+                .conn
+                .prepare(&format!("INSERT INTO {} VALUES (?, ?)", map_table))?;
+            for raw in &raw_values {
+                let utc = time_parser::parse_local_time(raw)
+                    .and_then(|t| time_parser::local_to_utc(t, tz));
+                if let Some(utc) = utc {
+                    let utc = utc.format(time_parser::TIME_FORMAT).to_string();
+                    stmt.execute([raw.as_str(), utc.as_str()])?;
+                }
+            }
+        }
+
+        self.conn.execute(&format!("DROP TABLE IF EXISTS {}", target_table), [])?;
+        self.conn.execute(
+            &format!(
+                "CREATE TEMP TABLE {} AS \
+                 SELECT t.rowid AS row_id, CAST(m.ts AS TIMESTAMP) AS ts \
+                 FROM {} t LEFT JOIN {} m ON CAST(t.\"{}\" AS VARCHAR) = m.raw",
+                target_table, table_name, map_table, fields.time_field
+            ),
+            [],
+        )?;
+
+        Ok(())
+    }
+
+    /// 未被任何配对或分组匹配占用的记录过滤条件，alias 为 "a" 或 "b"
+    fn unmatched_filter(alias: &str) -> String {
+        let (pair_column, side) = if alias == "a" { ("a_row", "A") } else { ("b_row", "B") };
+        format!(
+            "{alias}.rowid NOT IN (SELECT {pair_column} FROM match_pairs) \
+             AND {alias}.rowid NOT IN (SELECT row_id FROM group_members WHERE side = '{side}')",
+            alias = alias,
+            pair_column = pair_column,
+            side = side
+        )
+    }
+
+    /// 重建一对一配对表 match_pairs(a_row, b_row, match_pass) 和分组匹配成员表 group_members(side, row_id)
+    fn reset_match_tables(&self) -> Result<()> {
+        self.conn.execute("DROP TABLE IF EXISTS match_pairs", [])?;
+        self.conn.execute(
+            "CREATE TEMP TABLE match_pairs (a_row BIGINT, b_row BIGINT, match_pass VARCHAR)",
+            [],
+        )?;
+        self.conn.execute("DROP TABLE IF EXISTS group_members", [])?;
+        self.conn.execute(
+            "CREATE TEMP TABLE group_members (side VARCHAR, row_id BIGINT)",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// 在剩余未配对记录中按关联字段分组，两侧金额合计一致（或在容差内）的组视为分组匹配
+    /// 至少一侧包含多条记录才算分组匹配，成员记录追加到 group_members，不再参与配对、不再计入单边账；
+    /// split_only 时只识别一侧恰好一条、另一侧多条的组（拆分付款 / 批量结算），用于一对一配对之前
+    fn build_group_matches(
+        &self,
+        match_config: &MatchConfig,
+        group_match: &GroupMatchConfig,
+        amount_a: &str,
+        amount_b: &str,
+        split_only: bool,
+    ) -> Result<Vec<HashMap<String, serde_json::Value>>> {
+        let group_shape = if split_only {
+            "((ga.row_count = 1 AND gb.row_count > 1) OR (ga.row_count > 1 AND gb.row_count = 1))"
+        } else {
+            "(ga.row_count > 1 OR gb.row_count > 1)"
+        };
+        let tolerance = Self::amount_tolerance_condition(match_config, "ga.amount_sum", "gb.amount_sum")
+            .map(|t| format!(" OR {}", t))
+            .unwrap_or_default();
+        let sign_filter = if match_config.require_sign_agreement {
+            " AND SIGN(ga.amount_sum) = SIGN(gb.amount_sum)"
+        } else {
+            ""
+        };
+
+        let group_side = |alias: &str, group_field: &str, id_field: &str, amount: &str| {
+            format!(
+                "SELECT CAST({alias}.\"{group}\" AS VARCHAR) AS group_key, \
+                        SUM({amount}) AS amount_sum, \
+                        COUNT(*) AS row_count, \
+                        COUNT(DISTINCT {alias}.normalized_status) AS status_count, \
+                        MIN({alias}.normalized_status) AS status, \
+                        STRING_AGG(CAST({alias}.\"{id}\" AS VARCHAR), ',' ORDER BY {alias}.rowid) AS ids, \
+                        LIST({alias}.rowid) AS row_ids \
+                 FROM source_{alias} {alias} \
+                 WHERE {filter} AND {alias}.\"{group}\" IS NOT NULL \
+                 GROUP BY 1",
+                alias = alias,
+                group = group_field,
+                id = id_field,
+                amount = amount,
+                filter = Self::unmatched_filter(alias)
+            )
+        };
+
+        let create_sql = format!(
+            "CREATE TEMP TABLE group_candidates AS \
+             WITH ga AS ({}), gb AS ({}) \
+             SELECT ga.group_key, ga.ids AS a_ids, gb.ids AS b_ids, \
+                    ga.row_count AS a_count, gb.row_count AS b_count, \
+                    ga.amount_sum AS a_amount, gb.amount_sum AS b_amount, \
+                    ga.status, ga.row_ids AS a_rows, gb.row_ids AS b_rows \
+             FROM ga INNER JOIN gb ON ga.group_key = gb.group_key \
+             WHERE {} \
+               AND ga.status_count = 1 AND gb.status_count = 1 AND ga.status = gb.status \
+               AND (ga.amount_sum = gb.amount_sum{}){}",
+            group_side("a", &group_match.source_a_group_field, &match_config.source_a_id_field, amount_a),
+            group_side("b", &group_match.source_b_group_field, &match_config.source_b_id_field, amount_b),
+            group_shape,
+            tolerance,
+            sign_filter
+        );
+        self.conn.execute("DROP TABLE IF EXISTS group_candidates", [])?;
+        self.conn.execute(&create_sql, [])?;
+
+        self.conn.execute(
+            "INSERT INTO group_members SELECT 'A', UNNEST(a_rows) FROM group_candidates",
+            [],
+        )?;
+        self.conn.execute(
+            "INSERT INTO group_members SELECT 'B', UNNEST(b_rows) FROM group_candidates",
+            [],
+        )?;
+
+        self.execute_query_to_json(
+            "SELECT group_key AS match_group, \
+                    a_ids AS \"sourceAIds\", b_ids AS \"sourceBIds\", \
+                    a_count AS \"sourceACount\", b_count AS \"sourceBCount\", \
+                    a_amount AS \"sourceAAmountSum\", b_amount AS \"sourceBAmountSum\", \
+                    status AS normalized_status \
+             FROM group_candidates ORDER BY group_key",
+        )
+    }
+
+    /// 对剩余单边账按ID相似度（忽略大小写，仅保留字母数字）寻找疑似匹配
+    /// 每条数据源A记录只保留得分最高且达到阈值的一条建议；sourceAId / sourceBId 为两侧原始 ID，人工确认时用于定位记录
+    fn find_suggested_matches(
+        &self,
+        match_config: &MatchConfig,
+        fuzzy_match: &FuzzyMatchConfig,
+    ) -> Result<Vec<HashMap<String, serde_json::Value>>> {
+        let normalized = |alias: &str, field: &str| {
+            format!(
+                "REGEXP_REPLACE(LOWER(CAST({}.\"{}\" AS VARCHAR)), '[^a-z0-9]', '', 'g')",
+                alias, field
+            )
+        };
+        let id_a = normalized("a", &match_config.source_a_id_field);
+        let id_b = normalized("b", &match_config.source_b_id_field);
+
+        // 两侧的 ID 各自在 CTE 中规范化一次，交叉连接时只比较规范化后的值
+        let score = match fuzzy_match.algorithm.as_str() {
+            "JARO_WINKLER" => "JARO_WINKLER_SIMILARITY(ka.norm_id, kb.norm_id)".to_string(),
+            "LEVENSHTEIN" => "1.0 - CAST(LEVENSHTEIN(ka.norm_id, kb.norm_id) AS DOUBLE) \
+                 / GREATEST(LENGTH(ka.norm_id), LENGTH(kb.norm_id))"
+                .to_string(),
+            other => anyhow::bail!("不支持的模糊匹配算法: {}", other),
+        };
+        let threshold = fuzzy_match.threshold.clamp(0.0, 1.0);
+
+        let sql = format!(
+            "WITH keys_a AS ( \
+                 SELECT a_row, norm_id FROM ( \
+                     SELECT a.rowid AS a_row, {id_a} AS norm_id FROM source_a a WHERE {filter_a} \
+                 ) WHERE LENGTH(norm_id) > 0 \
+             ), keys_b AS ( \
+                 SELECT b_row, norm_id FROM ( \
+                     SELECT b.rowid AS b_row, {id_b} AS norm_id FROM source_b b WHERE {filter_b} \
+                 ) WHERE LENGTH(norm_id) > 0 \
+             ), candidates AS ( \
+                 SELECT ka.a_row, kb.b_row, ROUND({score}, 4) AS match_score \
+                 FROM keys_a ka CROSS JOIN keys_b kb \
+             ), best AS ( \
+                 SELECT * FROM candidates WHERE match_score >= {threshold} \
+                 QUALIFY ROW_NUMBER() OVER (PARTITION BY a_row ORDER BY match_score DESC, b_row) = 1 \
+             ) \
+             SELECT a.*, b.*, best.match_score, \
+                    CAST(a.\"{raw_a}\" AS VARCHAR) AS \"sourceAId\", \
+                    CAST(b.\"{raw_b}\" AS VARCHAR) AS \"sourceBId\" \
+             FROM best \
+             INNER JOIN source_a a ON a.rowid = best.a_row \
+             INNER JOIN source_b b ON b.rowid = best.b_row \
+             ORDER BY best.match_score DESC",
+            score = score,
+            filter_a = Self::unmatched_filter("a"),
+            filter_b = Self::unmatched_filter("b"),
+            id_a = id_a,
+            id_b = id_b,
+            raw_a = match_config.source_a_id_field,
+            raw_b = match_config.source_b_id_field,
+            threshold = threshold
+        );
+
+        self.execute_query_to_json(&sql)
+    }
+
+    /// 根据两侧有序的匹配键构造 JOIN 条件
+    fn key_join_condition(order_keys: &[String], bank_keys: &[String]) -> Result<String> {
+        if order_keys.is_empty() || order_keys.len() != bank_keys.len() {
+            anyhow::bail!(
+                "匹配键数量不一致：数据源A {} 个，数据源B {} 个",
+                order_keys.len(),
+                bank_keys.len()
+            );
+        }
+
+        Ok(order_keys
+            .iter()
+            .zip(bank_keys)
+            .map(|(a, b)| format!("a.\"{}\" = b.\"{}\"", a, b))
+            .collect::<Vec<_>>()
+            .join(" AND "))
+    }
+
+    /// 构造金额容差判断条件；未配置容差时返回 None
+    /// 绝对容差与百分比容差任一满足即视为容差内匹配
+    fn amount_tolerance_condition(
+        match_config: &MatchConfig,
+        amount_a: &str,
+        amount_b: &str,
+    ) -> Option<String> {
+        let absolute = match_config.amount_tolerance.max(0.0);
+        let percent = match_config.amount_tolerance_percent.max(0.0);
+        if absolute == 0.0 && percent == 0.0 {
+            return None;
+        }
+
+        Some(format!(
+            "ABS({} - {}) <= GREATEST({}, ABS({}) * {} / 100)",
+            amount_a, amount_b, absolute, amount_b, percent
+        ))
+    }
+
     fn execute_query_to_json(&self, sql: &str) -> Result<Vec<HashMap<String, serde_json::Value>>> {
         // 使用临时表方式避免DuckDB的列名问题
         // 先将查询结果存入临时表，再从临时表读取
@@ -336,17 +1850,20 @@ impl DataProcessor {
         let create_temp_sql = format!("CREATE TEMP TABLE {} AS {}", temp_table_name, sql);
         self.conn.execute(&create_temp_sql, [])?;
         
-        // 获取列名
+        // 获取列名及列类型
         let columns_sql = format!(
-            "SELECT column_name FROM information_schema.columns WHERE table_name = '{}' ORDER BY ordinal_position",
+            "SELECT column_name, data_type FROM information_schema.columns WHERE table_name = '{}' ORDER BY ordinal_position",
             temp_table_name
         );
         
-        let column_names: Vec<String> = {
+        let columns: Vec<(String, String)> = {
             let mut column_stmt = self.conn.prepare(&columns_sql)?;
-            let mapped_rows = column_stmt.query_map([], |row| row.get::<_, String>(0))?;
+            let mapped_rows = column_stmt.query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?;
             mapped_rows.filter_map(|r| r.ok()).collect()
         };
+        let column_names: Vec<String> = columns.iter().map(|(name, _)| name.clone()).collect();
         
         // 如果没有列名，清理并返回空
         if column_names.is_empty() {
@@ -354,8 +1871,12 @@ impl DataProcessor {
             return Ok(Vec::new());
         }
         
-        // 读取数据
-        let select_sql = format!("SELECT * FROM {}", temp_table_name);
+        // 读取数据：定点小数、时间等类型转为字符串（如 100.50、YYYY-MM-DD HH:MM:SS）
+        let select_list: Vec<String> = columns
+            .iter()
+            .map(|(name, data_type)| Self::json_select_expr(name, data_type))
+            .collect();
+        let select_sql = format!("SELECT {} FROM {}", select_list.join(", "), temp_table_name);
         let result = {
             let mut stmt = self.conn.prepare(&select_sql)?;
             let mut rows = stmt.query([])?;
@@ -389,5 +1910,123 @@ impl DataProcessor {
         
         Ok(result)
     }
+
+    /// 读取查询结果时的列表达式：字符串、整数、浮点、布尔直接读取，其余类型先做转换
+    /// 金额（DECIMAL）转为字符串，保证结果中的金额是精确值
+    fn json_select_expr(column_name: &str, data_type: &str) -> String {
+        let column = format!("\"{}\"", column_name.replace('"', "\"\""));
+        let data_type = data_type.to_uppercase();
+        let is_native = matches!(
+            data_type.as_str(),
+            "VARCHAR" | "BOOLEAN" | "DOUBLE" | "FLOAT" | "TINYINT" | "SMALLINT" | "INTEGER" | "BIGINT"
+        );
+        if is_native {
+            column
+        } else if data_type == "HUGEINT" {
+            format!("CAST({} AS BIGINT) AS {}", column, column)
+        } else {
+            format!("CAST({} AS VARCHAR) AS {}", column, column)
+        }
+    }
+}
+
+/// 解析状态码范围，如 "00-09" 或 "100-199"，返回 (下限, 上限)
+pub fn parse_status_range(pattern: &str) -> Result<(f64, f64)> {
+    let (min, max) = pattern
+        .trim()
+        .split_once('-')
+        .with_context(|| format!("状态码范围格式应为 最小值-最大值: {}", pattern))?;
+    let parse = |value: &str| -> Result<f64> {
+        value
+            .trim()
+            .parse::<f64>()
+            .with_context(|| format!("状态码范围不是有效数字: {}", pattern))
+    };
+    let (min, max) = (parse(min)?, parse(max)?);
+    if min > max {
+        anyhow::bail!("状态码范围下限大于上限: {}", pattern);
+    }
+    Ok((min, max))
+}
+
+/// 解析 DIVIDE_NUMBER 的除数（定点小数，不能为 0）
+pub fn parse_divisor(value: &str) -> Result<rust_decimal::Decimal> {
+    let divisor = rust_decimal::Decimal::from_str(value.trim())
+        .with_context(|| format!("除数不是有效数字: {}", value))?;
+    if divisor.is_zero() {
+        anyhow::bail!("除数不能为 0");
+    }
+    Ok(divisor.normalize())
+}
+
+/// 除数的倒数为有限小数时返回倒数（如 100 → 0.01），否则返回 None
+fn exact_reciprocal(divisor: rust_decimal::Decimal) -> Option<rust_decimal::Decimal> {
+    let reciprocal = rust_decimal::Decimal::ONE.checked_div(divisor)?.normalize();
+    if reciprocal.scale() <= 10 && reciprocal.checked_mul(divisor)? == rust_decimal::Decimal::ONE {
+        Some(reciprocal)
+    } else {
+        None
+    }
+}
+
+/// 将字符串转义为 SQL 字符串字面量
+fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn execute_all(processor: &DataProcessor, statements: &[&str]) {
+        for sql in statements {
+            processor.conn.execute(sql, []).unwrap();
+        }
+    }
+
+    fn amount_fields(amount_field: &str) -> SourceFields {
+        SourceFields {
+            amount_field: amount_field.to_string(),
+            time_field: String::new(),
+            currency_field: String::new(),
+            fee_field: String::new(),
+            timezone: "UTC".to_string(),
+        }
+    }
+
+    #[test]
+    fn split_payout_is_grouped_instead_of_paired_one_to_one() {
+        let processor = DataProcessor::new().unwrap();
+        execute_all(
+            &processor,
+            &[
+                "CREATE TABLE source_a (order_id VARCHAR, order_amount DECIMAL(18,4), normalized_status VARCHAR)",
+                "INSERT INTO source_a VALUES ('P1', 100, 'SUCCESS')",
+                "CREATE TABLE source_b (bank_id VARCHAR, bank_amount DECIMAL(18,4), normalized_status VARCHAR)",
+                "INSERT INTO source_b VALUES ('P1', 60, 'SUCCESS'), ('P1', 40, 'SUCCESS')",
+            ],
+        );
+        let match_config: MatchConfig = serde_json::from_value(serde_json::json!({
+            "sourceAIdField": "order_id",
+            "sourceAStatusMapping": [],
+            "sourceBIdField": "bank_id",
+            "sourceBStatusMapping": [],
+            "groupMatch": { "enabled": true, "sourceAGroupField": "order_id", "sourceBGroupField": "bank_id" }
+        }))
+        .unwrap();
+
+        let result = processor
+            .perform_reconciliation(&match_config, &amount_fields("order_amount"), &amount_fields("bank_amount"))
+            .unwrap();
+
+        assert_eq!(result.grouped_matched.len(), 1);
+        let group = &result.grouped_matched[0];
+        assert_eq!(group["match_group"], serde_json::json!("P1"));
+        assert_eq!(group["sourceAIds"], serde_json::json!("P1"));
+        assert_eq!(group["sourceBIds"], serde_json::json!("P1,P1"));
+        assert!(result.matched.is_empty());
+        assert!(result.diff_amount.is_empty());
+        assert!(result.only_in_a.is_empty());
+        assert!(result.only_in_b.is_empty());
+    }
+}
@@ -0,0 +1,327 @@
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone};
+use chrono_tz::Tz;
+
+/// 清洗后时间字段统一使用的文本格式
+pub const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+pub fn parse_timezone(name: &str) -> Result<Tz> {
+    name.trim()
+        .parse()
+        .map_err(|_| anyhow::anyhow!("无效的时区: {}", name))
+}
+
+/// Excel 序列日期的起点（1900 日期系统，已包含 1900-02-29 的历史误差）
+const EXCEL_EPOCH: (i32, u32, u32) = (1899, 12, 30);
+
+/// 西语、葡语、印尼语月份名称（含缩写）到英文缩写的映射
+const MONTH_NAMES: &[(&str, &str)] = &[
+    // 葡萄牙语
+    ("janeiro", "Jan"), ("fevereiro", "Feb"), ("fev", "Feb"), ("março", "Mar"), ("marco", "Mar"),
+    ("abril", "Apr"), ("abr", "Apr"), ("maio", "May"), ("mai", "May"), ("junho", "Jun"),
+    ("julho", "Jul"), ("agosto", "Aug"), ("ago", "Aug"), ("setembro", "Sep"), ("set", "Sep"),
+    ("outubro", "Oct"), ("out", "Oct"), ("novembro", "Nov"), ("dezembro", "Dec"), ("dez", "Dec"),
+    // 西班牙语
+    ("enero", "Jan"), ("ene", "Jan"), ("febrero", "Feb"), ("marzo", "Mar"), ("mayo", "May"),
+    ("junio", "Jun"), ("julio", "Jul"), ("septiembre", "Sep"), ("setiembre", "Sep"), ("sept", "Sep"),
+    ("octubre", "Oct"), ("noviembre", "Nov"), ("diciembre", "Dec"), ("dic", "Dec"),
+    // 印尼语
+    ("januari", "Jan"), ("februari", "Feb"), ("maret", "Mar"), ("mei", "May"), ("juni", "Jun"),
+    ("juli", "Jul"), ("agustus", "Aug"), ("agt", "Aug"), ("oktober", "Oct"), ("okt", "Oct"),
+    ("desember", "Dec"), ("des", "Dec"),
+];
+
+/// 将原始时间值换算为目标时区的本地时间
+/// 配置了解析格式（DATE_PARSE）时优先按格式依次尝试；
+/// ORDER_TIME_TIMESTAMP 按 Unix 时间戳（秒或毫秒）解析；其他规则按数据源时区的本地时间解析，
+/// 若文本自带时区偏移（如 2025-12-30T10:30:00-03:00）则以偏移为准
+pub fn convert_time(
+    value: &str,
+    rule_type: &str,
+    patterns: &[String],
+    source_tz: Tz,
+    target_tz: Tz,
+) -> Option<NaiveDateTime> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+
+    let instant = if let Some(local) = parse_with_patterns(value, patterns) {
+        local_to_utc(local, source_tz)?
+    } else if rule_type == "ORDER_TIME_TIMESTAMP" {
+        parse_epoch(value)?
+    } else if let Some(with_offset) = parse_time_with_offset(value) {
+        with_offset
+    } else {
+        local_to_utc(parse_local_time(value)?, source_tz)?
+    };
+
+    Some(target_tz.from_utc_datetime(&instant).naive_local())
+}
+
+/// 解析 Unix 时间戳，返回 UTC 时间；绝对值大于 1e11 视为毫秒
+pub fn parse_epoch(value: &str) -> Option<NaiveDateTime> {
+    let number: f64 = value.trim().parse().ok()?;
+    let millis = if number.abs() > 1e11 { number } else { number * 1000.0 };
+    DateTime::from_timestamp_millis(millis.round() as i64).map(|t| t.naive_utc())
+}
+
+/// 解析带时区偏移的时间文本，返回 UTC 时间
+fn parse_time_with_offset(value: &str) -> Option<NaiveDateTime> {
+    const OFFSET_FORMATS: &[&str] = &["%Y-%m-%d %H:%M:%S%.f%:z", "%Y-%m-%d %H:%M:%S%.f%z"];
+
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .or_else(|| {
+            OFFSET_FORMATS
+                .iter()
+                .find_map(|f| DateTime::parse_from_str(value, f).ok())
+        })
+        .map(|t| t.naive_utc())
+}
+
+/// 解析常见格式的本地时间（不含时区）；仅有日期时取当天零点
+pub fn parse_local_time(value: &str) -> Option<NaiveDateTime> {
+    const DATETIME_FORMATS: &[&str] = &[
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M",
+        "%Y/%m/%d %H:%M:%S",
+        "%Y/%m/%d %H:%M",
+    ];
+    const DATE_FORMATS: &[&str] = &["%Y-%m-%d", "%Y/%m/%d"];
+
+    let value = value.trim();
+    DATETIME_FORMATS
+        .iter()
+        .find_map(|f| NaiveDateTime::parse_from_str(value, f).ok())
+        .or_else(|| {
+            DATE_FORMATS
+                .iter()
+                .find_map(|f| NaiveDate::parse_from_str(value, f).ok())
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+        })
+}
+
+/// 将某时区的本地时间换算为 UTC（夏令时重叠取较早时刻，跳过的时段顺延一小时）
+pub fn local_to_utc(local: NaiveDateTime, tz: Tz) -> Option<NaiveDateTime> {
+    tz.from_local_datetime(&local)
+        .earliest()
+        .or_else(|| {
+            tz.from_local_datetime(&(local + chrono::Duration::hours(1)))
+                .earliest()
+        })
+        .map(|t| t.naive_utc())
+}
+
+/// 按配置的格式依次尝试解析（每个格式内可用 | 分隔多个备选格式）
+/// 支持 dd/MM/yyyy 风格、strftime 风格（含 %）以及 EXCEL（Excel 序列日期）
+pub fn parse_with_patterns(value: &str, patterns: &[String]) -> Option<NaiveDateTime> {
+    let translated = translate_month_names(value);
+
+    patterns
+        .iter()
+        .flat_map(|p| p.split('|'))
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .find_map(|pattern| {
+            if pattern.eq_ignore_ascii_case("EXCEL") {
+                return parse_excel_serial(value);
+            }
+            let format = to_chrono_format(pattern);
+            NaiveDateTime::parse_from_str(&translated, &format)
+                .ok()
+                .or_else(|| {
+                    NaiveDate::parse_from_str(&translated, &format)
+                        .ok()
+                        .and_then(|d| d.and_hms_opt(0, 0, 0))
+                })
+        })
+}
+
+/// 将 dd/MM/yyyy HH:mm:ss 风格的格式转换为 chrono 格式；已是 strftime 风格（含 %）的原样返回
+/// 单引号内为原样字符，如 dd 'de' MMMM 'de' yyyy
+pub fn to_chrono_format(pattern: &str) -> String {
+    if pattern.contains('%') {
+        return pattern.to_string();
+    }
+
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut format = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let ch = chars[i];
+        if ch == '\'' {
+            // 引号内为原样字符，'' 表示单引号本身
+            i += 1;
+            while i < chars.len() {
+                if chars[i] == '\'' {
+                    if chars.get(i + 1) == Some(&'\'') {
+                        format.push('\'');
+                        i += 2;
+                        continue;
+                    }
+                    break;
+                }
+                format.push(chars[i]);
+                i += 1;
+            }
+            i += 1;
+            continue;
+        }
+
+        let mut count = 1;
+        while chars.get(i + count) == Some(&ch) {
+            count += 1;
+        }
+        let token = match ch {
+            'y' if count == 2 => "%y".to_string(),
+            'y' => "%Y".to_string(),
+            'M' if count >= 3 => "%b".to_string(),
+            'M' => "%m".to_string(),
+            'd' => "%d".to_string(),
+            'H' => "%H".to_string(),
+            'h' => "%I".to_string(),
+            'm' => "%M".to_string(),
+            's' => "%S".to_string(),
+            'S' if matches!(count, 3 | 6 | 9) => format!("%{}f", count),
+            'S' => "%f".to_string(),
+            'a' => "%p".to_string(),
+            'E' if count >= 4 => "%A".to_string(),
+            'E' => "%a".to_string(),
+            'X' | 'Z' | 'x' => "%z".to_string(),
+            _ => ch.to_string().repeat(count),
+        };
+        format.push_str(&token);
+        i += count;
+    }
+    format
+}
+
+/// 将葡语、西语、印尼语月份名称替换为英文缩写，便于按 MMM 解析
+fn translate_month_names(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut word = String::new();
+    let flush = |word: &mut String, result: &mut String| {
+        let lower = word.to_lowercase();
+        let lower = lower.trim_end_matches('.');
+        match MONTH_NAMES.iter().find(|(name, _)| *name == lower) {
+            Some((_, english)) => result.push_str(english),
+            None => result.push_str(word),
+        }
+        word.clear();
+    };
+
+    for ch in value.chars() {
+        if ch.is_alphabetic() {
+            word.push(ch);
+        } else {
+            if !word.is_empty() {
+                flush(&mut word, &mut result);
+            }
+            result.push(ch);
+        }
+    }
+    if !word.is_empty() {
+        flush(&mut word, &mut result);
+    }
+    result
+}
+
+/// 解析 Excel 序列日期（如 45291.5 表示 2023-12-31 12:00:00）
+pub fn parse_excel_serial(value: &str) -> Option<NaiveDateTime> {
+    let serial: f64 = value.trim().parse().ok()?;
+    if !(1.0..2_958_466.0).contains(&serial) {
+        return None;
+    }
+    let (year, month, day) = EXCEL_EPOCH;
+    let base = NaiveDate::from_ymd_opt(year, month, day)?.and_hms_opt(0, 0, 0)?;
+    let seconds = (serial * 86_400.0).round() as i64;
+    base.checked_add_signed(chrono::Duration::seconds(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn datetime(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, TIME_FORMAT).unwrap()
+    }
+
+    fn patterns(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_localized_month_names() {
+        assert_eq!(
+            parse_with_patterns("15 de março de 2024", &patterns(&["dd 'de' MMMM 'de' yyyy"])),
+            Some(datetime("2024-03-15 00:00:00"))
+        );
+        assert_eq!(
+            parse_with_patterns("03-dic-2023 14:05", &patterns(&["dd-MMM-yyyy HH:mm"])),
+            Some(datetime("2023-12-03 14:05:00"))
+        );
+        assert_eq!(
+            parse_with_patterns("17 Agustus 2024", &patterns(&["dd MMMM yyyy"])),
+            Some(datetime("2024-08-17 00:00:00"))
+        );
+        // 缩写后带点
+        assert_eq!(
+            parse_with_patterns("01/Out./2024", &patterns(&["dd/MMM./yyyy"])),
+            Some(datetime("2024-10-01 00:00:00"))
+        );
+    }
+
+    #[test]
+    fn tries_alternative_patterns_in_order() {
+        let patterns = patterns(&["dd/MM/yyyy HH:mm|dd/MM/yyyy"]);
+        assert_eq!(
+            parse_with_patterns("03/04/2024 10:30", &patterns),
+            Some(datetime("2024-04-03 10:30:00"))
+        );
+        assert_eq!(
+            parse_with_patterns("03/04/2024", &patterns),
+            Some(datetime("2024-04-03 00:00:00"))
+        );
+        assert_eq!(parse_with_patterns("2024-04-03", &patterns), None);
+    }
+
+    #[test]
+    fn parses_excel_serial_dates() {
+        assert_eq!(parse_excel_serial("45291.5"), Some(datetime("2023-12-31 12:00:00")));
+        assert_eq!(parse_excel_serial("1"), Some(datetime("1899-12-31 00:00:00")));
+        assert_eq!(parse_excel_serial("0"), None);
+        assert_eq!(parse_excel_serial("abc"), None);
+        assert_eq!(
+            parse_with_patterns("45292", &patterns(&["dd/MM/yyyy|EXCEL"])),
+            Some(datetime("2024-01-01 00:00:00"))
+        );
+    }
+
+    #[test]
+    fn converts_between_timezones() {
+        let sao_paulo = parse_timezone("America/Sao_Paulo").unwrap();
+        let utc = parse_timezone("UTC").unwrap();
+        assert_eq!(
+            convert_time("2025-12-30 10:30:00", "ORDER_TIME_NORMAL", &[], sao_paulo, utc),
+            Some(datetime("2025-12-30 13:30:00"))
+        );
+        // 自带偏移时以偏移为准
+        assert_eq!(
+            convert_time("2025-12-30T10:30:00+01:00", "ORDER_TIME_NORMAL", &[], sao_paulo, utc),
+            Some(datetime("2025-12-30 09:30:00"))
+        );
+        // 秒与毫秒时间戳
+        assert_eq!(
+            convert_time("1767096000", "ORDER_TIME_TIMESTAMP", &[], sao_paulo, utc),
+            Some(datetime("2025-12-30 12:00:00"))
+        );
+        assert_eq!(
+            convert_time("1767096000000", "ORDER_TIME_TIMESTAMP", &[], sao_paulo, utc),
+            Some(datetime("2025-12-30 12:00:00"))
+        );
+        assert!(parse_timezone("Mars/Olympus").is_err());
+    }
+}
@@ -14,6 +14,8 @@ export interface ChannelConfig {
     timezone: string;
     removeDuplicate: boolean;
     mappings: ColumnMapping[];
+    rowFilters?: RowFilter[]; // 行过滤（清洗后、去重和匹配前执行）
+    fileOptions?: SourceFileOptions; // 源文件读取选项（Excel 工作表等）
   };
   
   // 数据源B配置
@@ -22,6 +24,8 @@ export interface ChannelConfig {
     timezone: string;
     removeDuplicate: boolean;
     mappings: ColumnMapping[];
+    rowFilters?: RowFilter[]; // 行过滤（清洗后、去重和匹配前执行）
+    fileOptions?: SourceFileOptions; // 源文件读取选项（Excel 工作表等）
   };
   
   // 匹配配置
@@ -30,9 +34,24 @@ export interface ChannelConfig {
     sourceAStatusMapping: StatusMapping[];
     sourceBIdField: string;
     sourceBStatusMapping: StatusMapping[];
+    sourceAKeyFields?: string[]; // 组合匹配键（有序），为空时使用 sourceAIdField
+    sourceBKeyFields?: string[]; // 组合匹配键（有序），为空时使用 sourceBIdField
+    matchPasses?: MatchPass[]; // 多轮级联匹配
+    groupMatch?: GroupMatchConfig; // 分组匹配（一对多/多对一）
+    fuzzyMatch?: FuzzyMatchConfig; // 模糊ID匹配建议
+    maxTimeDiffHours?: number; // 两侧时间最大允许差值（小时），0 表示不限制
+    reconciliationTimezone?: string; // 对账统一时区，默认 UTC
     useHistoricalSourceA?: boolean;
     useHistoricalSourceB?: boolean;
     historyDays?: number;
+    amountTolerance?: number; // 金额绝对容差
+    amountTolerancePercent?: number; // 金额百分比容差（%）
+    amountCurrency?: string; // 对账金额币种（ISO 4217），决定金额小数位数
+    currencyPrecisions?: Record<string, number>; // 币种小数位数覆盖，如 { JPY: 0 }
+    currencyConversion?: CurrencyConversionConfig; // 比较前按汇率换算一侧金额
+    fee?: FeeConfig; // 净额侧还原为总额后再比较
+    requireSignAgreement?: boolean; // 要求两侧金额符号一致才能匹配
+    defaultStatus?: string; // 未映射状态使用的默认标准状态
   };
 }
 
@@ -49,28 +68,89 @@ export interface FileConfig {
   header: number;
   timezone: string;
   removeDuplicate: boolean;
+  rowFilters?: RowFilter[];
+  fileOptions?: SourceFileOptions;
+}
+
+// 源文件读取选项：CSV 和 Excel 通用（分隔符、引号、编码仅用于 CSV）
+export interface SourceFileOptions {
+  sheetName?: string; // Excel 工作表名称，为空时读取第一个工作表
+  skipFooter?: number; // 忽略文件末尾的行数（合计行等）
+  skipRows?: number; // 没有表头行（header 为 0）时，数据之前需跳过的行数
+  delimiter?: string; // CSV 分隔符，默认 ","；制表符写作 "\t" 或 "TAB"
+  quote?: string; // CSV 引号字符，默认 '"'
+  decimalSeparator?: '.' | ','; // 金额小数点，默认 "."
+  encoding?: string; // 文件编码（UTF-8、GBK、ISO-8859-1、UTF-16 等），默认 UTF-8
+  // 列类型覆盖：源列名 -> 类型；未覆盖时映射的 ID/字符串/状态/币种/时间列按 VARCHAR 读取
+  columnTypes?: Record<string, 'VARCHAR' | 'BIGINT' | 'DOUBLE' | 'DATE' | 'TIMESTAMP' | 'BOOLEAN'>;
+}
+
+// 行过滤：INCLUDE 只保留满足条件的行，EXCLUDE 排除满足条件的行
+export interface RowFilter {
+  name?: string; // 过滤说明，写入被排除行的 excluded_by 列
+  action: 'INCLUDE' | 'EXCLUDE';
+  condition: RuleCondition;
 }
 
 export interface ColumnMapping {
   id: string;
   sourceColumn: string;
-  fieldType: 'OrderTime' | 'OrderStatus' | 'OrderString' | 'OrderAmount';
+  fieldType: 'OrderTime' | 'OrderStatus' | 'OrderString' | 'OrderAmount' | 'OrderFee' | 'Currency';
   fieldName: string;
   ruleType: string;
   ruleConfig: string;
   saveOriginal: boolean;
   formatRules: FormatRule[];
+  expression?: string; // 计算表达式，如 amount - fee、concat(branch, '-', account)
+  sign?: AmountSignConfig; // 金额符号规则（借贷分列 / 借贷标识）
+}
+
+// 金额符号规则
+export interface AmountSignConfig {
+  mode: 'DEBIT_CREDIT' | 'INDICATOR';
+  debitColumn?: string; // DEBIT_CREDIT：借方列
+  creditColumn?: string; // DEBIT_CREDIT：贷方列
+  indicatorColumn?: string; // INDICATOR：借贷标识列
+  debitValues?: string[]; // 表示借方的标识值，默认 D / DR / DEBIT
+  debitNegative?: boolean; // 借方记为负数，默认 true
 }
 
 export interface FormatRule {
   type: 'pre' | 'post';
   operation: string;
   value: string;
+  group?: number; // REGEX_EXTRACT 捕获组序号，默认 1
+  replacement?: string; // REGEX_REPLACE 替换内容，可用 \1 引用捕获组
+  condition?: RuleCondition; // 规则生效条件，为空时对所有行生效
+  missingPolicy?: 'KEEP' | 'NULL' | 'FLAG'; // LOOKUP 未匹配时的处理，默认 KEEP
+}
+
+// 对照表（字典），LOOKUP 规则的 value 填写表名
+export interface LookupTable {
+  id: string;
+  name: string;
+  description?: string;
+  entries: LookupEntry[];
+  updatedAt?: string;
+}
+
+export interface LookupEntry {
+  key: string;
+  value: string;
+}
+
+// 格式规则生效条件：按同一行另一列的值判断
+export interface RuleCondition {
+  column: string;
+  operator: 'EQUALS' | 'NOT_EQUALS' | 'IN' | 'REGEX' | 'GT' | 'GTE' | 'LT' | 'LTE';
+  value: string; // IN 时为逗号分隔列表
 }
 
 export interface StatusMapping {
   sourceStatus: string[];
   targetStatus: string;
+  // 匹配方式，默认 EXACT；优先级 EXACT > RANGE > WILDCARD > REGEX
+  matchType?: 'EXACT' | 'WILDCARD' | 'REGEX' | 'RANGE';
 }
 
 export interface MatchConfig {
@@ -78,9 +158,69 @@ export interface MatchConfig {
   sourceAStatusMapping: StatusMapping[];
   sourceBIdField: string;
   sourceBStatusMapping: StatusMapping[];
+  sourceAKeyFields?: string[];
+  sourceBKeyFields?: string[];
+  matchPasses?: MatchPass[];
+  groupMatch?: GroupMatchConfig;
+  fuzzyMatch?: FuzzyMatchConfig;
+  maxTimeDiffHours?: number;
+  reconciliationTimezone?: string;
   useHistoricalSourceA?: boolean;
   useHistoricalSourceB?: boolean;
   historyDays?: number;
+  amountTolerance?: number;
+  amountTolerancePercent?: number;
+  amountCurrency?: string;
+  currencyPrecisions?: Record<string, number>;
+  currencyConversion?: CurrencyConversionConfig;
+  fee?: FeeConfig;
+  requireSignAgreement?: boolean;
+  defaultStatus?: string;
+}
+
+// 级联匹配中的一轮，每轮只处理前面轮次未匹配的记录
+export interface MatchPass {
+  name: string;
+  sourceAKeyFields: string[];
+  sourceBKeyFields: string[];
+  maxTimeDiffHours?: number; // 本轮时间窗口，为空时使用全局配置
+}
+
+// 分组匹配：按批次号/日期等关联字段分组后比较两侧金额合计
+export interface GroupMatchConfig {
+  enabled: boolean;
+  sourceAGroupField: string;
+  sourceBGroupField: string;
+}
+
+// 模糊ID匹配：对剩余单边账按ID相似度给出疑似匹配（不自动匹配）
+export interface FuzzyMatchConfig {
+  enabled: boolean;
+  algorithm: 'JARO_WINKLER' | 'LEVENSHTEIN';
+  threshold: number; // 0~1
+}
+
+// 币种换算：将一侧金额按汇率表换算为 amountCurrency
+export interface CurrencyConversionConfig {
+  enabled: boolean;
+  convertSide: 'A' | 'B';
+  sourceCurrency?: string; // 该侧无 Currency 字段或币种为空时的默认币种
+}
+
+// 手续费：netSide 一侧为扣费后净额；映射了 OrderFee 字段时直接使用，否则按 fixedFee + 总额 × percentFee% 计算
+export interface FeeConfig {
+  enabled: boolean;
+  netSide: 'A' | 'B';
+  fixedFee?: number;
+  percentFee?: number; // 百分比，如 1.5 表示 1.5%
+}
+
+// 汇率：rateDate 当日起 1 baseCurrency = rate quoteCurrency
+export interface FxRate {
+  rateDate: string; // YYYY-MM-DD
+  baseCurrency: string;
+  quoteCurrency: string;
+  rate: string;
 }
 
 export interface ReconciliationResult {
@@ -88,6 +228,12 @@ export interface ReconciliationResult {
   onlyInA: any[];
   onlyInB: any[];
   diffAmount: any[];
+  matchedWithTolerance?: any[];
+  statusMismatch?: any[];
+  groupedMatched?: any[];
+  suggestedMatches?: any[];
+  excludedA?: any[]; // 被行过滤排除的记录
+  excludedB?: any[];
 }
 
 export interface ReconciliationStats {
@@ -95,6 +241,12 @@ export interface ReconciliationStats {
   onlyInSourceACount: number;
   onlyInSourceBCount: number;
   diffAmountCount: number;
+  toleranceMatchedCount?: number;
+  statusMismatchCount?: number;
+  groupedMatchedCount?: number;
+  suggestedMatchCount?: number;
+  excludedSourceACount?: number;
+  excludedSourceBCount?: number;
   totalSourceA: number;
   totalSourceB: number;
 }
@@ -117,6 +269,13 @@ export interface ReconciliationTask {
   stats: ReconciliationStats;
   usedHistoricalSourceA: boolean;
   usedHistoricalSourceB: boolean;
+  unmappedStatuses?: UnmappedStatus[]; // 本次未被状态映射覆盖的原始状态
+}
+
+export interface UnmappedStatus {
+  sourceName: string;
+  rawStatus: string;
+  count: number;
 }
 
 export interface OrderFile {
@@ -165,6 +324,23 @@ export const FORMAT_OPERATIONS = [
   { value: 'ADD_CHAR_PRE', label: 'ADD_CHAR_PRE' },
   { value: 'ADD_CHAR_AFTER', label: 'ADD_CHAR_AFTER' },
   { value: 'XENDIT_TIME', label: 'XENDIT_TIME' },
+  { value: 'DATE_PARSE', label: 'DATE_PARSE' },
+  { value: 'NEGATE_NUMBER', label: 'NEGATE_NUMBER' },
+  { value: 'REGEX_EXTRACT', label: 'REGEX_EXTRACT' },
+  { value: 'REGEX_REPLACE', label: 'REGEX_REPLACE' },
+  { value: 'LOOKUP', label: 'LOOKUP' },
+];
+
+// 格式规则条件、行过滤的运算符
+export const CONDITION_OPERATORS: { value: RuleCondition['operator']; label: string }[] = [
+  { value: 'EQUALS', label: '等于' },
+  { value: 'NOT_EQUALS', label: '不等于' },
+  { value: 'IN', label: '属于（逗号分隔）' },
+  { value: 'REGEX', label: '匹配正则' },
+  { value: 'GT', label: '大于' },
+  { value: 'GTE', label: '大于等于' },
+  { value: 'LT', label: '小于' },
+  { value: 'LTE', label: '小于等于' },
 ];
 
 export const TIMEZONES = [
@@ -214,3 +390,28 @@ export async function exportConfig(config: ChannelConfig, filePath: string): Pro
 export async function importConfig(filePath: string): Promise<ChannelConfig> {
   return await invoke<ChannelConfig>('import_config', { filePath });
 }
+
+// 工具函数：加载汇率表
+export async function loadFxRates(): Promise<FxRate[]> {
+  return await invoke<FxRate[]>('load_fx_rates');
+}
+
+// 工具函数：从 CSV 导入汇率，返回导入条数
+export async function importFxRates(filePath: string): Promise<number> {
+  return await invoke<number>('import_fx_rates', { filePath });
+}
+
+// 工具函数：加载对照表
+export async function loadLookupTables(): Promise<LookupTable[]> {
+  return await invoke<LookupTable[]>('load_lookup_tables');
+}
+
+// 工具函数：新增或更新对照表
+export async function saveLookupTable(table: LookupTable): Promise<LookupTable> {
+  return await invoke<LookupTable>('save_lookup_table', { table });
+}
+
+// 工具函数：删除对照表
+export async function deleteLookupTable(tableId: string): Promise<void> {
+  await invoke('delete_lookup_table', { tableId });
+}